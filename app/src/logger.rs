@@ -0,0 +1,37 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// The crates of the app, which are the only ones whose messages are printed. The libraries they
+/// use log a lot more than is useful to see.
+const APP_CRATES: [&str; 4] = [
+    "rotatar",
+    "rotatar_backend",
+    "iced_frontend",
+    "tauri_frontend",
+];
+
+/// Prints messages logged by the app to stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let krate = metadata.target().split("::").next().unwrap_or_default();
+        metadata.level() <= log::max_level() && APP_CRATES.contains(&krate)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Sets up printing the warnings and information logged by the app.
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
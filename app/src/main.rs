@@ -1,6 +1,8 @@
 use rotatar_types::{Args, ArgsError, FrontendError, Parser, ValidArgs};
 use thiserror::Error;
 
+mod logger;
+
 #[derive(Debug, Error)]
 enum AppError {
     #[error("Invalid arguments were passed")]
@@ -13,14 +15,16 @@ enum AppError {
 
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
+    logger::init();
     let args: ValidArgs = Args::parse().try_into()?;
 
     let config = rotatar_backend::run(&args).await?;
+    let animations = rotatar_backend::Animations::load(&config)?;
     match args.frontend() {
         #[cfg(feature = "iced-frontend")]
-        rotatar_types::Frontend::Iced => iced_frontend::run(args, config).await?,
+        rotatar_types::Frontend::Iced => iced_frontend::run(args, config, animations).await?,
         #[cfg(feature = "tauri-frontend")]
-        rotatar_types::Frontend::Tauri => tauri_frontend::run(config),
+        rotatar_types::Frontend::Tauri => tauri_frontend::run(args, config, animations),
     }
 
     Ok(())
@@ -0,0 +1,74 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_channel::Receiver;
+use rotatar_types::AfkConfig;
+
+use crate::interval;
+
+/// How often the AFK timer checks for inactivity.
+const AFK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Keeps track of when the last input happened. Clones share the same time, so any clone can be
+/// woken up.
+#[derive(Clone, Debug)]
+pub struct AfkTimer {
+    last_activity: Arc<Mutex<Instant>>,
+}
+
+impl AfkTimer {
+    pub fn new() -> Self {
+        Self {
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Records that some input just happened.
+    pub fn wake(&self) {
+        if let Ok(mut last_activity) = self.last_activity.lock() {
+            *last_activity = Instant::now();
+        }
+    }
+
+    /// Gets the time since the last input.
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity
+            .lock()
+            .map(|last_activity| last_activity.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for AfkTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Watches the AFK timer, returning a receiver that gets the current sleeping frame whenever it
+/// changes. `None` is sent when the avatar wakes up.
+pub async fn get_afk_status(afk_timer: AfkTimer, config: AfkConfig) -> Receiver<Option<usize>> {
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let timeout = Duration::from_secs(config.timeout());
+        let frame_duration = config.frame_duration().max(1) as u128;
+        let frame_count = config.sleeping_images().len().max(1);
+        let mut last_sleeping = None;
+        interval!(AFK_POLL_INTERVAL, sender, {
+            let idle_time = afk_timer.idle_time();
+            let sleeping = idle_time
+                .checked_sub(timeout)
+                .map(|asleep_for| (asleep_for.as_millis() / frame_duration) as usize % frame_count);
+            if sleeping != last_sleeping {
+                if sender.send(sleeping).await.is_err() {
+                    break;
+                }
+                last_sleeping = sleeping;
+            }
+        });
+    });
+    out_receiver
+}
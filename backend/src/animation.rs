@@ -0,0 +1,491 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Cursor},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use async_channel::Receiver;
+use image::{
+    AnimationDecoder, ImageError, ImageFormat, ImageResult, RgbaImage,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    error::{LimitError, LimitErrorKind},
+};
+use rotatar_types::{Config, FrameSequence, ImageSource, LoopMode, SheetCell};
+
+use crate::{Error, svg};
+
+/// The longest time the animation ticker sleeps for, so that a newly shown animation starts
+/// playing quickly.
+const MAX_TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Frame delays at or below this are treated the same way browsers treat them, as
+/// [`DEFAULT_FRAME_DELAY`].
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// The most memory, in bytes, that the decoded frames of every animation and sprite sheet cell of
+/// a config may take up together. They are all kept for as long as the config is used.
+const MAX_DECODED_BYTES: usize = 512 * 1024 * 1024;
+
+/// What a single frame of an animation shows.
+#[derive(Debug)]
+pub enum FrameImage {
+    /// A frame decoded from an animated image file.
+    Decoded {
+        image: RgbaImage,
+        /// The frame encoded as a PNG, for frontends that can't show raw pixels.
+        png: OnceLock<Vec<u8>>,
+    },
+    /// A frame of a frame sequence, which is its own image file.
+    File(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct AnimationFrame {
+    image: FrameImage,
+    delay: Duration,
+}
+
+/// The frames of an animation, together with their timing.
+#[derive(Debug)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    mode: LoopMode,
+}
+
+/// Every animation used by a config, keyed by the image source it was loaded from. Sprite sheet
+/// cells are kept here as well, as animations with a single frame.
+#[derive(Debug, Default)]
+pub struct Animations {
+    animations: HashMap<ImageSource, Animation>,
+    /// The size of every image source of the config, read once when the animations are loaded.
+    sizes: HashMap<ImageSource, (u32, u32)>,
+}
+
+/// The frame each shown animation is at. Images that are not animated are always at frame 0.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnimationFrames {
+    frames: HashMap<ImageSource, usize>,
+}
+
+/// What should be drawn for an image source at a specific frame.
+#[derive(Clone, Copy, Debug)]
+pub enum DisplayedFrame<'a> {
+    File(&'a PathBuf),
+    /// A decoded frame, together with the path of the image it was decoded from and its index.
+    Decoded(&'a Path, usize, &'a FrameImage),
+    /// A cell cut out of a sprite sheet.
+    Cell(&'a SheetCell, &'a FrameImage),
+}
+
+impl FrameImage {
+    /// Gets the frame encoded as a PNG. Decoded frames are only encoded the first time this is
+    /// called. Returns `None` for frames that are image files.
+    pub fn png(&self) -> Option<ImageResult<&[u8]>> {
+        let FrameImage::Decoded { image, png } = self else {
+            return None;
+        };
+        if let Some(png) = png.get() {
+            return Some(Ok(png));
+        }
+        let mut encoded = Vec::new();
+        if let Err(error) = image.write_to(&mut Cursor::new(&mut encoded), ImageFormat::Png) {
+            return Some(Err(error));
+        }
+        Some(Ok(png.get_or_init(|| encoded)))
+    }
+}
+
+impl AnimationFrames {
+    /// Gets the frame an image source is at.
+    pub fn get(&self, source: &ImageSource) -> usize {
+        self.frames.get(source).copied().unwrap_or_default()
+    }
+}
+
+impl AnimationFrame {
+    pub fn image(&self) -> &FrameImage {
+        &self.image
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+impl Animation {
+    /// Decodes the image at the path if it is an animated GIF, APNG or WebP. Returns `Ok(None)`
+    /// for any other image, including images in those formats that only have a single frame.
+    ///
+    /// The decoded frames may take up at most `memory_left` bytes, which they are then taken off.
+    /// Decoding stops with [`LimitErrorKind::InsufficientMemory`] as soon as they go over it.
+    pub fn decode(path: &Path, memory_left: &mut usize) -> ImageResult<Option<Self>> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_ascii_lowercase());
+        let reader = || -> ImageResult<_> { Ok(BufReader::new(File::open(path)?)) };
+        let frames = match extension.as_ref().and_then(|extension| extension.to_str()) {
+            Some("gif") => GifDecoder::new(reader()?)?.into_frames(),
+            Some("png" | "apng") => {
+                let decoder = PngDecoder::new(reader()?)?;
+                if !decoder.is_apng()? {
+                    return Ok(None);
+                }
+                decoder.apng()?.into_frames()
+            }
+            Some("webp") => {
+                let decoder = WebPDecoder::new(reader()?)?;
+                if !decoder.has_animation() {
+                    return Ok(None);
+                }
+                decoder.into_frames()
+            }
+            _ => return Ok(None),
+        };
+        let mut memory = *memory_left;
+        let frames = frames
+            .map(|frame| {
+                let frame = frame?;
+                let delay = Duration::from(frame.delay());
+                let image = frame.into_buffer();
+                memory = memory.checked_sub(image.as_raw().len()).ok_or_else(|| {
+                    ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
+                })?;
+                Ok(AnimationFrame {
+                    delay: if delay <= MIN_FRAME_DELAY {
+                        DEFAULT_FRAME_DELAY
+                    } else {
+                        delay
+                    },
+                    image: FrameImage::Decoded {
+                        image,
+                        png: OnceLock::new(),
+                    },
+                })
+            })
+            .collect::<ImageResult<Vec<_>>>()?;
+        if frames.len() < 2 {
+            return Ok(None);
+        }
+        *memory_left = memory;
+        Ok(Some(Self {
+            frames,
+            mode: LoopMode::Loop,
+        }))
+    }
+
+    /// Cuts a cell out of a decoded sprite sheet, creating an animation with that single frame.
+    pub fn from_cell(sheet: &RgbaImage, cell: &SheetCell) -> Self {
+        let image =
+            image::imageops::crop_imm(sheet, cell.x(), cell.y(), cell.width(), cell.height())
+                .to_image();
+        Self {
+            frames: vec![AnimationFrame {
+                image: FrameImage::Decoded {
+                    image,
+                    png: OnceLock::new(),
+                },
+                delay: DEFAULT_FRAME_DELAY,
+            }],
+            mode: LoopMode::Loop,
+        }
+    }
+
+    /// Creates an animation from a frame sequence. Returns `None` if the sequence has no frames.
+    pub fn from_sequence(sequence: &FrameSequence) -> Option<Self> {
+        if sequence.frames().is_empty() {
+            return None;
+        }
+        Some(Self {
+            frames: sequence
+                .frames()
+                .iter()
+                .map(|frame| AnimationFrame {
+                    image: FrameImage::File(frame.path().clone()),
+                    delay: Duration::from_millis(frame.duration().max(1)),
+                })
+                .collect(),
+            mode: sequence.mode(),
+        })
+    }
+
+    pub fn frames(&self) -> &Vec<AnimationFrame> {
+        &self.frames
+    }
+
+    /// The memory, in bytes, that the decoded frames of the animation take up.
+    pub fn decoded_bytes(&self) -> usize {
+        self.frames
+            .iter()
+            .map(|frame| match &frame.image {
+                FrameImage::Decoded { image, .. } => image.as_raw().len(),
+                FrameImage::File(_) => 0,
+            })
+            .sum()
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&AnimationFrame> {
+        self.frames.get(index % self.frames.len())
+    }
+
+    pub fn mode(&self) -> LoopMode {
+        self.mode
+    }
+
+    /// The order frames are played in during a single cycle of the animation.
+    fn timeline(&self) -> Vec<usize> {
+        let mut timeline: Vec<usize> = (0..self.frames.len()).collect();
+        if self.mode == LoopMode::PingPong {
+            timeline.extend((1..self.frames.len().saturating_sub(1)).rev());
+        }
+        timeline
+    }
+
+    /// Gets the index of the frame that should be shown after the animation has been playing for
+    /// `elapsed`, together with how long that frame keeps being shown. A frame that is held
+    /// forever reports [`Duration::MAX`].
+    pub fn frame_at(&self, elapsed: Duration) -> (usize, Duration) {
+        let timeline = self.timeline();
+        let cycle: Duration = timeline.iter().map(|index| self.frames[*index].delay).sum();
+        if self.mode == LoopMode::Once && elapsed >= cycle {
+            return (self.frames.len() - 1, Duration::MAX);
+        }
+        let mut remaining =
+            Duration::from_nanos((elapsed.as_nanos() % cycle.as_nanos().max(1)) as u64);
+        for index in timeline {
+            let delay = self.frames[index].delay;
+            if remaining < delay {
+                return (index, delay - remaining);
+            }
+            remaining -= delay;
+        }
+        (0, self.frames[0].delay)
+    }
+}
+
+impl Animations {
+    /// Loads every animation and sprite sheet cell used by the config. Images that fail to decode
+    /// are reported and then shown as still images, cells of sheets that fail to decode are not
+    /// shown.
+    ///
+    /// Fails if the decoded frames would take up more than [`MAX_DECODED_BYTES`] together.
+    pub fn load(config: &Config) -> Result<Self, Error> {
+        Self::load_within(config, MAX_DECODED_BYTES)
+    }
+
+    fn load_within(config: &Config, memory: usize) -> Result<Self, Error> {
+        let too_large = |path: &Path| {
+            Error::InvalidConfig(format!(
+                "Decoding '{}' takes the animations and sprite sheet cells of the config over {} \
+                 MiB of memory",
+                path.display(),
+                memory / 1024 / 1024
+            ))
+        };
+        let mut memory_left = memory;
+        let mut animations = HashMap::new();
+        let mut sheets: HashMap<&PathBuf, Option<RgbaImage>> = HashMap::new();
+        for source in config.image_sources() {
+            if animations.contains_key(source) {
+                continue;
+            }
+            let animation = match source {
+                ImageSource::Path(path) => match Animation::decode(path, &mut memory_left) {
+                    Ok(animation) => animation,
+                    Err(ImageError::Limits(_)) => return Err(too_large(path)),
+                    Err(error) => {
+                        log::warn!("Could not decode '{}': {error}", path.display());
+                        None
+                    }
+                },
+                ImageSource::Sequence(sequence) => Animation::from_sequence(sequence),
+                ImageSource::Cell(cell) => sheets
+                    .entry(cell.sheet())
+                    .or_insert_with(|| match image::open(cell.sheet()) {
+                        Ok(sheet) => Some(sheet.into_rgba8()),
+                        Err(error) => {
+                            log::warn!(
+                                "Could not decode sprite sheet '{}': {error}",
+                                cell.sheet().display()
+                            );
+                            None
+                        }
+                    })
+                    .as_ref()
+                    .map(|sheet| Animation::from_cell(sheet, cell)),
+            };
+            // Decoding animated images already took their frames off the memory that is left.
+            if let (ImageSource::Cell(cell), Some(animation)) = (source, &animation) {
+                memory_left = memory_left
+                    .checked_sub(animation.decoded_bytes())
+                    .ok_or_else(|| too_large(cell.sheet()))?;
+            }
+            if let Some(animation) = animation {
+                animations.insert(source.clone(), animation);
+            }
+        }
+        let sizes = config
+            .image_sources()
+            .into_iter()
+            .filter_map(|source| {
+                let size = read_size(animations.get(source), source)?;
+                Some((source.clone(), size))
+            })
+            .collect();
+        Ok(Self { animations, sizes })
+    }
+
+    pub fn get(&self, source: &ImageSource) -> Option<&Animation> {
+        self.animations.get(source)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.animations.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ImageSource, &Animation)> {
+        self.animations.iter()
+    }
+
+    /// Gets the width and height of an image source of the config. SVG images report the size they
+    /// are meant to be shown at.
+    pub fn size(&self, source: &ImageSource) -> Option<(u32, u32)> {
+        self.sizes.get(source).copied()
+    }
+
+    /// Works out what should be drawn for an image source at the frame.
+    pub fn display<'a>(
+        &'a self,
+        source: &'a ImageSource,
+        frame: usize,
+    ) -> Option<DisplayedFrame<'a>> {
+        let Some(animation) = self.get(source) else {
+            return match source {
+                ImageSource::Path(path) => Some(DisplayedFrame::File(path)),
+                ImageSource::Sequence(_) | ImageSource::Cell(_) => None,
+            };
+        };
+        let index = frame % animation.frames.len();
+        match (&animation.frames[index].image, source) {
+            (FrameImage::File(path), _) => Some(DisplayedFrame::File(path)),
+            (image, ImageSource::Path(path)) => Some(DisplayedFrame::Decoded(path, index, image)),
+            (image, ImageSource::Cell(cell)) => Some(DisplayedFrame::Cell(cell, image)),
+            (_, ImageSource::Sequence(_)) => None,
+        }
+    }
+}
+
+/// Reads the width and height of an image source, from the image file when it is not an animation.
+fn read_size(animation: Option<&Animation>, source: &ImageSource) -> Option<(u32, u32)> {
+    let frame = animation.and_then(|animation| animation.frames.first());
+    match (frame.map(|frame| &frame.image), source) {
+        (Some(FrameImage::Decoded { image, .. }), _) => Some(image.dimensions()),
+        (Some(FrameImage::File(path)), _) | (None, ImageSource::Path(path))
+            if svg::is_svg(path) =>
+        {
+            svg::size(path).ok()
+        }
+        (Some(FrameImage::File(path)), _) | (None, ImageSource::Path(path)) => {
+            image::image_dimensions(path).ok()
+        }
+        (None, _) => None,
+    }
+}
+
+/// Drives the animation clock, returning a receiver that gets the frame of every shown animation
+/// whenever any of them changes. `shown_images` is called to find out which images are currently
+/// shown, which includes layers and props.
+///
+/// Looping animations share a single clock, so they stay in step with each other however often the
+/// shown images change. Animations that play once start from when they are shown.
+pub async fn get_animation_frames<F>(
+    animations: Arc<Animations>,
+    shown_images: F,
+) -> Receiver<AnimationFrames>
+where
+    F: Fn() -> Vec<ImageSource> + Send + 'static,
+{
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let start = Instant::now();
+        let mut shown_since: HashMap<ImageSource, Instant> = HashMap::new();
+        let mut last_frames = AnimationFrames::default();
+        while !sender.is_closed() {
+            let images = shown_images();
+            shown_since.retain(|image, _| images.contains(image));
+            let mut frames = AnimationFrames::default();
+            let mut next_tick = MAX_TICK_INTERVAL;
+            for image in images {
+                let Some(animation) = animations.get(&image) else {
+                    continue;
+                };
+                let since = *shown_since
+                    .entry(image.clone())
+                    .or_insert_with(Instant::now);
+                let (frame, frame_left) = if animation.mode() == LoopMode::Once {
+                    animation.frame_at(since.elapsed())
+                } else {
+                    animation.frame_at(start.elapsed())
+                };
+                frames.frames.insert(image, frame);
+                next_tick = next_tick.min(frame_left);
+            }
+            if frames != last_frames {
+                if sender.send(frames.clone()).await.is_err() {
+                    break;
+                }
+                last_frames = frames;
+            }
+            tokio::time::sleep(next_tick).await;
+        }
+    });
+    out_receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use image::{Frame, codecs::gif::GifEncoder};
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::{self, directory};
+
+    /// Writes a GIF with three 4x4 frames, which take up 64 bytes each once decoded, returning a
+    /// config that shows it.
+    fn config(directory: &Path) -> Config {
+        let path = directory.join("animated.gif");
+        let mut encoder = GifEncoder::new(File::create(&path).unwrap());
+        encoder
+            .encode_frames((0..3).map(|shade| {
+                Frame::new(RgbaImage::from_pixel(4, 4, image::Rgba([shade, 0, 0, 255])))
+            }))
+            .unwrap();
+        test_util::config(json!({ "idle_images": [path] }))
+    }
+
+    #[test]
+    fn animations_are_loaded_within_the_memory_limit() {
+        let directory = directory(&[]);
+        let config = config(directory.path());
+        let animations = Animations::load_within(&config, 192).unwrap();
+        let animation = animations.get(config.image_sources()[0]).unwrap();
+        assert_eq!(animation.frames().len(), 3);
+        assert_eq!(animation.decoded_bytes(), 192);
+    }
+
+    #[test]
+    fn animations_over_the_memory_limit_are_rejected() {
+        let directory = directory(&[]);
+        let config = config(directory.path());
+        assert!(matches!(
+            Animations::load_within(&config, 191),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
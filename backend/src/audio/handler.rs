@@ -125,7 +125,9 @@ impl AudioHandler {
     /// 1. No config was found.
     /// 2. An error occurred while playing the stream.
     /// 3. The audio receiver received a stop signal (`AudioMessage::Stop`)
-    pub async fn play(self) -> AudioHandlerResult {
+    ///
+    /// New thresholds sent with `AudioMessage::SetConfig` are used from the next callback on.
+    pub async fn play(mut self) -> AudioHandlerResult {
         if let Some(ref config) = self.config {
             let sampling_rate = config.sample_rate.0;
             // We store these in here so the exist between all calls of the data callback.
@@ -134,6 +136,8 @@ impl AudioHandler {
             let last_sensitivity = arctex!(0.0);
             let error = arctex!(None);
             let error_clone = error.clone();
+            let audio_config = arctex!(self.audio_config);
+            let callback_audio_config = audio_config.clone();
             let data_callback_sender = self.sender.clone();
             let error_callback_sender = self.sender.clone();
             let _ = self
@@ -143,10 +147,16 @@ impl AudioHandler {
             match self.input_devices[self.current_input_index].build_input_stream(
                 config,
                 move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if let (Ok(mut last_time), Ok(mut sensitivity), Ok(mut last_sensitivity)) = (
+                    if let (
+                        Ok(mut last_time),
+                        Ok(mut sensitivity),
+                        Ok(mut last_sensitivity),
+                        Ok(audio_config),
+                    ) = (
                         last_time.lock(),
                         sensitivity.lock(),
                         last_sensitivity.lock(),
+                        callback_audio_config.lock(),
                     ) {
                         // Get the time since the last call.
                         let delta = Instant::now().duration_since(*last_time).as_secs_f32();
@@ -170,14 +180,14 @@ impl AudioHandler {
                             .iter()
                             .max()
                             .unwrap_or(&0)
-                            .min(&self.audio_config.max_magnitude());
+                            .min(&audio_config.max_magnitude());
                         data_callback_sender
                             .send_blocking(Message::MagnitudeChanged(maximum_magnitude))
                             .unwrap();
                         // If the maximum magnitude is greater than the "speaking threshold," sensitivity
                         // is set to 1.0. If not, the sensitivity is decreased at a rate of 3.0 sensitivity/second
                         // Calculated using the delta found before.
-                        if maximum_magnitude > self.audio_config.magnitude_threshold() {
+                        if maximum_magnitude > audio_config.magnitude_threshold() {
                             *sensitivity = 1.0;
                         } else {
                             *sensitivity = (*sensitivity - (3.0 * delta)).max(0.0);
@@ -221,6 +231,12 @@ impl AudioHandler {
                                             stopped = true;
                                             break;
                                         }
+                                        AudioMessage::SetConfig(new_config) => {
+                                            if let Ok(mut audio_config) = audio_config.lock() {
+                                                *audio_config = new_config;
+                                            }
+                                            self.audio_config = new_config;
+                                        }
                                     }
                                 }
                             } else {
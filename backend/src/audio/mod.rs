@@ -4,6 +4,8 @@ pub use error::AudioError;
 pub use handler::AudioHandler;
 pub use status::AudioStatus;
 
+use rotatar_types::AudioConfig;
+
 use crate::Message;
 
 mod error;
@@ -17,6 +19,8 @@ pub struct AudioHandlerResult {
 
 pub enum AudioMessage {
     Stop,
+    /// Replaces the thresholds of the running audio handler, after the config was reloaded.
+    SetConfig(AudioConfig),
 }
 
 impl AudioHandlerResult {
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use async_channel::Receiver;
+use rotatar_types::BlinkConfig;
+
+/// Schedules blinks at random intervals, returning a receiver that gets `true` when the eyes close
+/// and `false` when they open again.
+pub async fn get_blinks(config: BlinkConfig) -> Receiver<bool> {
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let min_interval = config.min_interval().min(config.max_interval());
+        let max_interval = config.max_interval().max(min_interval);
+        loop {
+            let interval = fastrand::u64(min_interval..=max_interval);
+            tokio::time::sleep(Duration::from_millis(interval)).await;
+            if sender.send(true).await.is_err() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(config.duration())).await;
+            if sender.send(false).await.is_err() {
+                break;
+            }
+        }
+    });
+    out_receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::time::Instant;
+
+    use super::*;
+
+    fn config(min_interval: u64, max_interval: u64, duration: u64) -> BlinkConfig {
+        serde_json::from_value(json!({
+            "min_interval": min_interval,
+            "max_interval": max_interval,
+            "duration": duration,
+        }))
+        .unwrap()
+    }
+
+    /// Waits for the next blink, returning how long the eyes stayed open before it and how long
+    /// they were closed for.
+    async fn blink(blinks: &Receiver<bool>) -> (Duration, Duration) {
+        let opened = Instant::now();
+        assert_eq!(blinks.recv().await, Ok(true));
+        let closed = Instant::now();
+        assert_eq!(blinks.recv().await, Ok(false));
+        (closed - opened, closed.elapsed())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn blinks_come_within_the_interval_and_last_for_the_duration() {
+        let blinks = get_blinks(config(100, 200, 50)).await;
+        for _ in 0..20 {
+            let (open, closed) = blink(&blinks).await;
+            assert!((100..=200).contains(&open.as_millis()), "{open:?}");
+            assert_eq!(closed, Duration::from_millis(50));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_minimum_above_the_maximum_is_swapped() {
+        let blinks = get_blinks(config(300, 100, 50)).await;
+        for _ in 0..20 {
+            let (open, _) = blink(&blinks).await;
+            assert!((100..=300).contains(&open.as_millis()), "{open:?}");
+        }
+    }
+}
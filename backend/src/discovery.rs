@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+
+use rotatar_types::ImageSource;
+
+use crate::Error;
+
+/// Finds the image for each section in `directory` by filling in `pattern`, in the order sections
+/// are numbered in. Every section without an image is listed in the error.
+pub fn discover(
+    directory: &Path,
+    pattern: &str,
+    (x_sections, y_sections): (i32, i32),
+) -> Result<Vec<ImageSource>, Error> {
+    let placeholders = placeholders(pattern)?;
+    for (name, sections) in [("x", x_sections), ("y", y_sections)] {
+        if sections > 1
+            && !placeholders
+                .iter()
+                .any(|placeholder| placeholder == name || placeholder == "index")
+        {
+            return Err(Error::InvalidConfig(format!(
+                "The image pattern '{pattern}' needs {{{name}}} or {{index}}, as there is more than one section in that direction"
+            )));
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut missing = Vec::new();
+    for y in 0..y_sections {
+        for x in 0..x_sections {
+            let index = y * x_sections + x;
+            let path = directory.join(fill(pattern, x, y, index));
+            if path.is_file() {
+                images.push(ImageSource::Path(path));
+            } else {
+                missing.push(format!("{x}x{y} ('{}')", path.display()));
+            }
+        }
+    }
+    if !missing.is_empty() {
+        return Err(Error::InvalidConfig(format!(
+            "The image pattern '{pattern}' in '{}' is missing {} of {} images, for the sections {}",
+            directory.display(),
+            missing.len(),
+            x_sections * y_sections,
+            missing.join(", ")
+        )));
+    }
+    Ok(images)
+}
+
+/// Gets the names of the placeholders in a pattern, checking that they are all known.
+fn placeholders(pattern: &str) -> Result<Vec<String>, Error> {
+    let mut names = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            return Err(Error::InvalidConfig(format!(
+                "The image pattern '{pattern}' has a '{{' without a matching '}}'"
+            )));
+        };
+        let placeholder = &rest[start + 1..start + length];
+        let (name, width) = placeholder.split_once(':').unwrap_or((placeholder, "0"));
+        if !matches!(name, "x" | "y" | "index") || width.parse::<usize>().is_err() {
+            return Err(Error::InvalidConfig(format!(
+                "The image pattern '{pattern}' has an unknown placeholder {{{placeholder}}}. Only {{x}}, {{y}} and {{index}} are supported, optionally with a width like {{x:2}}"
+            )));
+        }
+        names.push(name.to_string());
+        rest = &rest[start + length + 1..];
+    }
+    Ok(names)
+}
+
+/// Replaces the placeholders of an already checked pattern.
+fn fill(pattern: &str, x: i32, y: i32, index: i32) -> PathBuf {
+    let mut filled = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}').unwrap_or(rest.len() - start);
+        filled.push_str(&rest[..start]);
+        let placeholder = &rest[start + 1..end];
+        let (name, width) = placeholder.split_once(':').unwrap_or((placeholder, "0"));
+        let width = width.parse().unwrap_or(0);
+        let value = match name {
+            "x" => x,
+            "y" => y,
+            _ => index,
+        };
+        filled.push_str(&format!("{value:0width$}"));
+        rest = rest.get(end + 1..).unwrap_or_default();
+    }
+    filled.push_str(rest);
+    PathBuf::from(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{directory, invalid_config_message};
+
+    #[test]
+    fn placeholders_are_padded_to_their_width() {
+        assert_eq!(
+            fill("{x:2}_{y:3}.png", 4, 12, 0),
+            PathBuf::from("04_012.png")
+        );
+        assert_eq!(fill("{x:2}.png", 123, 0, 0), PathBuf::from("123.png"));
+        assert_eq!(fill("{x}x{y}.png", 4, 12, 0), PathBuf::from("4x12.png"));
+    }
+
+    #[test]
+    fn index_counts_sections_row_by_row() {
+        assert_eq!(
+            fill("face{index:3}.png", 1, 2, 7),
+            PathBuf::from("face007.png")
+        );
+        assert_eq!(
+            fill("row{y}/{index}.png", 1, 2, 7),
+            PathBuf::from("row2/7.png")
+        );
+    }
+
+    #[test]
+    fn known_placeholders_are_listed() {
+        assert_eq!(
+            placeholders("{y}/{x:2}-{index}.png").unwrap(),
+            ["y", "x", "index"]
+        );
+        assert!(placeholders("plain.png").unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        for pattern in ["{z}.png", "{x:two}.png", "{}.png", "{X}.png"] {
+            let message = invalid_config_message(placeholders(pattern));
+            assert!(message.contains("unknown placeholder"), "{message}");
+        }
+    }
+
+    #[test]
+    fn unclosed_placeholders_are_rejected() {
+        let message = invalid_config_message(placeholders("{x}_{y.png"));
+        assert!(message.contains("without a matching"), "{message}");
+    }
+
+    #[test]
+    fn images_are_found_in_section_order() {
+        let directory = directory(&["0x0.png", "1x0.png", "0x1.png", "1x1.png"]);
+        let images = discover(directory.path(), "{x}x{y}.png", (2, 2)).unwrap();
+        let names: Vec<_> = images
+            .iter()
+            .map(|image| match image {
+                ImageSource::Path(path) => {
+                    path.strip_prefix(directory.path()).unwrap().to_path_buf()
+                }
+                other => panic!("Expected a path, found {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            ["0x0.png", "1x0.png", "0x1.png", "1x1.png"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn patterns_need_a_placeholder_for_each_direction_with_sections() {
+        let directory = directory(&[]);
+        let message = invalid_config_message(discover(directory.path(), "{x}.png", (2, 2)));
+        assert!(message.contains("needs {y} or {index}"), "{message}");
+        let message = invalid_config_message(discover(directory.path(), "{y}.png", (2, 1)));
+        assert!(message.contains("needs {x} or {index}"), "{message}");
+    }
+
+    #[test]
+    fn every_missing_image_is_reported() {
+        let directory = directory(&["0.png", "2.png"]);
+        let message = invalid_config_message(discover(directory.path(), "{index}.png", (2, 2)));
+        assert!(message.contains("missing 2 of 4 images"), "{message}");
+        assert!(message.contains("1x0 ("), "{message}");
+        assert!(message.contains("1x1 ("), "{message}");
+        assert!(!message.contains("0x0 ("), "{message}");
+    }
+}
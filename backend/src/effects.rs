@@ -0,0 +1,312 @@
+use image::{GrayImage, RgbaImage, imageops};
+use rotatar_types::{Color, EffectsConfig};
+
+/// Renders the outline and drop shadow of an image, following its alpha, for frontends that can't
+/// draw them themselves. The outline is drawn over the shadow, and the shadow is cast by the
+/// outlined image, the same way chained CSS drop shadows are.
+///
+/// `pixels` are the RGBA pixels of an image that is `size` large. `scale` is how many of its pixels
+/// make up one pixel of the sizes in the config, which differs for rasterized SVG images. The
+/// result is larger than the image by [`margin`] pixels on each side, so that nothing is cut off.
+/// Returns `None` when there are no effects to draw.
+pub fn render(
+    (width, height): (u32, u32),
+    pixels: &[u8],
+    effects: &EffectsConfig,
+    scale: f32,
+) -> Option<RgbaImage> {
+    if !effects.is_drawn() {
+        return None;
+    }
+    let margin = margin(effects, scale);
+    let mut mask = GrayImage::new(width + margin * 2, height + margin * 2);
+    let pixel_count = (width * height) as usize;
+    for (index, pixel) in pixels.chunks_exact(4).take(pixel_count).enumerate() {
+        let (x, y) = (index as u32 % width, index as u32 / width);
+        mask.put_pixel(x + margin, y + margin, image::Luma([pixel[3]]));
+    }
+    if let Some(outline) = effects.outline() {
+        mask = dilate(&mask, outline.width() * scale);
+    }
+
+    let mut rendered = RgbaImage::new(mask.width(), mask.height());
+    if let Some(shadow) = effects.shadow() {
+        let blurred = if shadow.blur() > 0.0 {
+            // CSS blur radiuses are twice the standard deviation.
+            imageops::blur(&mask, shadow.blur() * scale / 2.0)
+        } else {
+            mask.clone()
+        };
+        let (offset_x, offset_y) = shadow.offset();
+        let (offset_x, offset_y) = (
+            (offset_x * scale).round() as i64,
+            (offset_y * scale).round() as i64,
+        );
+        for (x, y, alpha) in blurred.enumerate_pixels() {
+            let (x, y) = (x as i64 + offset_x, y as i64 + offset_y);
+            if x >= 0 && y >= 0 && x < rendered.width() as i64 && y < rendered.height() as i64 {
+                blend(
+                    &mut rendered,
+                    (x as u32, y as u32),
+                    shadow.color(),
+                    alpha[0],
+                );
+            }
+        }
+    }
+    if let Some(outline) = effects.outline() {
+        for (x, y, alpha) in mask.enumerate_pixels() {
+            blend(&mut rendered, (x, y), outline.color(), alpha[0]);
+        }
+    }
+    Some(rendered)
+}
+
+/// How far the effects reach past each edge of an image, in pixels of the image.
+pub fn margin(effects: &EffectsConfig, scale: f32) -> u32 {
+    (effects.margin() * scale).ceil() as u32
+}
+
+/// Grows the mask by `radius` pixels in every direction.
+///
+/// Each row of the disk is a horizontal run, so the mask is first spread sideways by the half width
+/// of a run, and then that is taken from the rows above and below that the run sits at. This only
+/// costs about `2 * radius` passes over the mask, rather than one for every pixel of the disk.
+fn dilate(mask: &GrayImage, radius: f32) -> GrayImage {
+    if radius <= 0.0 {
+        return mask.clone();
+    }
+    let reach = radius.ceil() as i64;
+    // The half width of each row of the disk, by how far the row is from its center.
+    let runs: Vec<(i64, usize)> = (-reach..=reach)
+        .filter_map(|offset_y| {
+            let half_width = (0..=reach)
+                .take_while(|x| ((x * x + offset_y * offset_y) as f32) <= radius * radius)
+                .last()?;
+            Some((offset_y, half_width as usize))
+        })
+        .collect();
+    let mut half_widths: Vec<usize> = runs.iter().map(|(_, half_width)| *half_width).collect();
+    half_widths.sort_unstable();
+    half_widths.dedup();
+
+    let (width, height) = (mask.width() as usize, mask.height() as usize);
+    let mut dilated = vec![0; width * height];
+    let mut spread = vec![0; width * height];
+    for half_width in half_widths {
+        for (row, spread_row) in mask
+            .as_raw()
+            .chunks_exact(width)
+            .zip(spread.chunks_exact_mut(width))
+        {
+            spread_max(row, half_width, spread_row);
+        }
+        for (offset_y, _) in runs.iter().filter(|(_, run)| *run == half_width) {
+            for y in 0..height {
+                let from = y as i64 + offset_y;
+                if from < 0 || from >= height as i64 {
+                    continue;
+                }
+                let from = from as usize * width;
+                for (alpha, spread) in dilated[y * width..(y + 1) * width]
+                    .iter_mut()
+                    .zip(&spread[from..from + width])
+                {
+                    *alpha = (*alpha).max(*spread);
+                }
+            }
+        }
+    }
+    GrayImage::from_raw(mask.width(), mask.height(), dilated).unwrap_or_else(|| mask.clone())
+}
+
+/// Sets each value of `spread` to the largest value of `row` that is at most `reach` away from it.
+/// This takes the same time however far the reach is, by splitting the row into blocks of the size
+/// of the window and keeping the running largest value of each block from both of its ends.
+fn spread_max(row: &[u8], reach: usize, spread: &mut [u8]) {
+    let window = reach * 2 + 1;
+    // The row is padded with `reach` zeros on both ends, so that windows never leave it.
+    let padded = |index: usize| {
+        index
+            .checked_sub(reach)
+            .and_then(|index| row.get(index))
+            .copied()
+            .unwrap_or_default()
+    };
+    let length = row.len() + reach * 2;
+    let mut from_start = vec![0; length];
+    let mut from_end = vec![0; length];
+    for index in 0..length {
+        from_start[index] = if index % window == 0 {
+            padded(index)
+        } else {
+            from_start[index - 1].max(padded(index))
+        };
+    }
+    for index in (0..length).rev() {
+        from_end[index] = if index == length - 1 || (index + 1) % window == 0 {
+            padded(index)
+        } else {
+            from_end[index + 1].max(padded(index))
+        };
+    }
+    // The window of each value starts at its index in the padded row.
+    for (index, value) in spread.iter_mut().enumerate() {
+        *value = from_end[index].max(from_start[index + window - 1]);
+    }
+}
+
+/// Draws the color over the pixel, with its alpha multiplied by `coverage`.
+fn blend(image: &mut RgbaImage, (x, y): (u32, u32), color: Color, coverage: u8) {
+    let alpha = color.a() as f32 / 255.0 * coverage as f32 / 255.0;
+    if alpha <= 0.0 {
+        return;
+    }
+    let pixel = image.get_pixel_mut(x, y);
+    let below = pixel[3] as f32 / 255.0;
+    let out = alpha + below * (1.0 - alpha);
+    let mix = |over: u8, under: u8| {
+        ((over as f32 * alpha + under as f32 * below * (1.0 - alpha)) / out).round() as u8
+    };
+    *pixel = image::Rgba([
+        mix(color.r(), pixel[0]),
+        mix(color.g(), pixel[1]),
+        mix(color.b(), pixel[2]),
+        (out * 255.0).round() as u8,
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    use super::*;
+
+    fn effects(effects: serde_json::Value) -> EffectsConfig {
+        serde_json::from_value(effects).unwrap()
+    }
+
+    /// Renders the effects of a single opaque pixel.
+    fn render_pixel(effects: &EffectsConfig) -> RgbaImage {
+        render((1, 1), &[255, 0, 0, 255], effects, 1.0).unwrap()
+    }
+
+    fn alphas(mask: &GrayImage) -> Vec<u8> {
+        mask.pixels().map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn dilating_grows_a_pixel_into_a_disk() {
+        let mut mask = GrayImage::new(7, 7);
+        mask.put_pixel(3, 3, image::Luma([255]));
+        let dilated = dilate(&mask, 2.0);
+        for (x, y, alpha) in dilated.enumerate_pixels() {
+            let (x, y) = (x as i32 - 3, y as i32 - 3);
+            let inside = x * x + y * y <= 4;
+            assert_eq!(alpha[0], if inside { 255 } else { 0 }, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn dilating_keeps_the_strongest_alpha() {
+        let mask = GrayImage::from_raw(5, 1, vec![0, 100, 0, 200, 0]).unwrap();
+        assert_eq!(alphas(&dilate(&mask, 1.0)), [100, 100, 200, 200, 200]);
+    }
+
+    #[test]
+    fn dilating_reaches_past_the_edges_without_wrapping() {
+        let mut mask = GrayImage::new(4, 3);
+        mask.put_pixel(0, 0, image::Luma([255]));
+        let dilated = dilate(&mask, 1.5);
+        #[rustfmt::skip]
+        let expected = [
+            255, 255, 0, 0,
+            255, 255, 0, 0,
+            0,   0,   0, 0,
+        ];
+        assert_eq!(alphas(&dilated), expected);
+    }
+
+    proptest! {
+        #[test]
+        fn dilating_matches_checking_every_pixel_of_the_disk(
+            (width, height, alphas) in (1..12u32, 1..12u32).prop_flat_map(|(width, height)| {
+                let alphas = prop::collection::vec(any::<u8>(), (width * height) as usize);
+                (Just(width), Just(height), alphas)
+            }),
+            radius in 0.1..8.0f32,
+        ) {
+            let mask = GrayImage::from_raw(width, height, alphas).unwrap();
+            let reach = radius.ceil() as i64;
+            let expected = GrayImage::from_fn(width, height, |x, y| {
+                let mut alpha = 0;
+                for offset_y in -reach..=reach {
+                    for offset_x in -reach..=reach {
+                        let (from_x, from_y) = (x as i64 + offset_x, y as i64 + offset_y);
+                        let distance = (offset_x * offset_x + offset_y * offset_y) as f32;
+                        let inside = distance <= radius * radius
+                            && (0..width as i64).contains(&from_x)
+                            && (0..height as i64).contains(&from_y);
+                        if inside {
+                            alpha = alpha.max(mask.get_pixel(from_x as u32, from_y as u32)[0]);
+                        }
+                    }
+                }
+                image::Luma([alpha])
+            });
+            prop_assert_eq!(dilate(&mask, radius), expected);
+        }
+    }
+
+    #[test]
+    fn dilating_by_nothing_keeps_the_mask() {
+        let mask = GrayImage::from_raw(3, 1, vec![0, 100, 0]).unwrap();
+        assert_eq!(alphas(&dilate(&mask, 0.0)), [0, 100, 0]);
+    }
+
+    #[test]
+    fn nothing_is_rendered_without_effects() {
+        assert!(render((1, 1), &[255, 0, 0, 255], &effects(json!({})), 1.0).is_none());
+    }
+
+    #[test]
+    fn the_outline_surrounds_the_image() {
+        let rendered = render_pixel(&effects(json!({
+            "outline": { "color": "#ffffff", "width": 1.0 },
+        })));
+        assert_eq!(rendered.dimensions(), (3, 3));
+        for (x, y, pixel) in rendered.enumerate_pixels() {
+            let (x, y) = (x as i32 - 1, y as i32 - 1);
+            let expected = if x * x + y * y <= 1 {
+                [255, 255, 255, 255]
+            } else {
+                [0, 0, 0, 0]
+            };
+            assert_eq!(pixel.0, expected, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn the_shadow_is_offset() {
+        let rendered = render_pixel(&effects(json!({
+            "shadow": { "color": "#000000", "offset": [2.0, 0.0], "blur": 0.0 },
+        })));
+        assert_eq!(rendered.dimensions(), (5, 5));
+        assert_eq!(rendered.get_pixel(4, 2).0, [0, 0, 0, 255]);
+        assert_eq!(rendered.get_pixel(2, 2).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn the_outline_casts_the_shadow_and_is_drawn_over_it() {
+        let rendered = render_pixel(&effects(json!({
+            "outline": { "color": "#ffffff", "width": 1.0 },
+            "shadow": { "color": "#000000", "offset": [1.0, 0.0], "blur": 0.0 },
+        })));
+        assert_eq!(rendered.dimensions(), (5, 5));
+        assert_eq!(rendered.get_pixel(3, 2).0, [255, 255, 255, 255]);
+        assert_eq!(rendered.get_pixel(4, 2).0, [0, 0, 0, 255]);
+        assert_eq!(rendered.get_pixel(1, 2).0, [255, 255, 255, 255]);
+        assert_eq!(rendered.get_pixel(0, 2).0, [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,335 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use async_channel::Receiver;
+use rdev::{EventType, Key};
+use rotatar_types::ExpressionConfig;
+
+use crate::{interval, keyboard};
+
+/// How often expressions are checked for having timed out.
+const EXPRESSION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A key combination, like `Ctrl+Shift+1`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hotkey {
+    modifiers: Modifiers,
+    key: Option<Key>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Modifiers {
+    control: bool,
+    shift: bool,
+    alt: bool,
+    meta: bool,
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(hotkey: &str) -> Result<Self, Self::Err> {
+        let mut parsed = Hotkey::default();
+        for part in hotkey.split('+').map(str::trim) {
+            let modifier = match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut parsed.modifiers.control,
+                "shift" => &mut parsed.modifiers.shift,
+                "alt" | "option" => &mut parsed.modifiers.alt,
+                "meta" | "super" | "win" | "cmd" | "command" => &mut parsed.modifiers.meta,
+                _ => {
+                    if parsed.key.is_some() {
+                        return Err(format!("'{hotkey}' has more than one key"));
+                    }
+                    parsed.key =
+                        Some(parse_key(part).ok_or_else(|| format!("Unknown key '{part}'"))?);
+                    continue;
+                }
+            };
+            *modifier = true;
+        }
+        if parsed.key.is_none() {
+            return Err(format!("'{hotkey}' needs a key that is not a modifier"));
+        }
+        Ok(parsed)
+    }
+}
+
+impl Modifiers {
+    /// Updates which modifiers are held. Returns false if the key is not a modifier.
+    fn update(&mut self, key: Key, held: bool) -> bool {
+        let modifier = match key {
+            Key::ControlLeft | Key::ControlRight => &mut self.control,
+            Key::ShiftLeft | Key::ShiftRight => &mut self.shift,
+            Key::Alt | Key::AltGr => &mut self.alt,
+            Key::MetaLeft | Key::MetaRight => &mut self.meta,
+            _ => return false,
+        };
+        *modifier = held;
+        true
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    const LETTERS: [Key; 26] = [
+        Key::KeyA,
+        Key::KeyB,
+        Key::KeyC,
+        Key::KeyD,
+        Key::KeyE,
+        Key::KeyF,
+        Key::KeyG,
+        Key::KeyH,
+        Key::KeyI,
+        Key::KeyJ,
+        Key::KeyK,
+        Key::KeyL,
+        Key::KeyM,
+        Key::KeyN,
+        Key::KeyO,
+        Key::KeyP,
+        Key::KeyQ,
+        Key::KeyR,
+        Key::KeyS,
+        Key::KeyT,
+        Key::KeyU,
+        Key::KeyV,
+        Key::KeyW,
+        Key::KeyX,
+        Key::KeyY,
+        Key::KeyZ,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Num0,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+    ];
+    const NUMPAD: [Key; 10] = [
+        Key::Kp0,
+        Key::Kp1,
+        Key::Kp2,
+        Key::Kp3,
+        Key::Kp4,
+        Key::Kp5,
+        Key::Kp6,
+        Key::Kp7,
+        Key::Kp8,
+        Key::Kp9,
+    ];
+    const FUNCTION: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
+    let name = name.to_ascii_lowercase();
+    let index = |digits: &str| digits.parse::<usize>().ok();
+    if let [character] = name.as_bytes() {
+        return match character {
+            b'a'..=b'z' => Some(LETTERS[(character - b'a') as usize]),
+            b'0'..=b'9' => Some(DIGITS[(character - b'0') as usize]),
+            _ => None,
+        };
+    }
+    if let Some(digit) = name.strip_prefix("numpad").and_then(index) {
+        return NUMPAD.get(digit).copied();
+    }
+    if let Some(number) = name.strip_prefix('f').and_then(index) {
+        return FUNCTION.get(number.checked_sub(1)?).copied();
+    }
+    Some(match name.as_str() {
+        "space" => Key::Space,
+        "tab" => Key::Tab,
+        "enter" | "return" => Key::Return,
+        "escape" | "esc" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        _ => return None,
+    })
+}
+
+/// Listens for the hotkeys of the expressions, returning a receiver that gets the name of an
+/// expression whenever its hotkey is pressed.
+///
+/// Keys are only compared against the hotkeys inside of the keyboard hook, nothing but the names
+/// of expressions ever leaves it.
+pub async fn get_expression_hotkeys(
+    expressions: &HashMap<String, ExpressionConfig>,
+) -> Receiver<String> {
+    let hotkeys = expressions
+        .iter()
+        .filter_map(|(name, expression)| {
+            let hotkey = expression.hotkey()?.parse::<Hotkey>().ok()?;
+            Some((hotkey, name.clone()))
+        })
+        .collect();
+    listen_for_hotkeys(hotkeys)
+}
+
+/// Listens for the hotkeys, returning a receiver that gets the name paired with a hotkey whenever
+/// it is pressed.
+pub(crate) fn listen_for_hotkeys(hotkeys: Vec<(Hotkey, String)>) -> Receiver<String> {
+    let (sender, out_receiver) = async_channel::unbounded();
+    if hotkeys.is_empty() {
+        return out_receiver;
+    }
+
+    let mut modifiers = Modifiers::default();
+    keyboard::on_key_event(move |event| {
+        let (key, held) = match event {
+            EventType::KeyPress(key) => (*key, true),
+            EventType::KeyRelease(key) => (*key, false),
+            _ => return !sender.is_closed(),
+        };
+        if modifiers.update(key, held) || !held {
+            return !sender.is_closed();
+        }
+        let pressed = Hotkey {
+            modifiers,
+            key: Some(key),
+        };
+        for (hotkey, name) in &hotkeys {
+            if *hotkey == pressed {
+                let _ = sender.try_send(name.clone());
+            }
+        }
+        !sender.is_closed()
+    });
+    out_receiver
+}
+
+/// Watches the active expression, returning a receiver that gets notified while it has been active
+/// for longer than its timeout. `expired` is called to find out if it has.
+pub async fn get_expression_reverts<F>(expired: F) -> Receiver<()>
+where
+    F: Fn() -> bool + Send + 'static,
+{
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        interval!(EXPRESSION_POLL_INTERVAL, sender, {
+            if expired() && sender.send(()).await.is_err() {
+                break;
+            }
+        });
+    });
+    out_receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hotkey(key: Key, control: bool, shift: bool, alt: bool, meta: bool) -> Hotkey {
+        Hotkey {
+            modifiers: Modifiers {
+                control,
+                shift,
+                alt,
+                meta,
+            },
+            key: Some(key),
+        }
+    }
+
+    #[test]
+    fn modifiers_are_parsed_in_any_case_and_order() {
+        assert_eq!(
+            "Ctrl+Shift+1".parse(),
+            Ok(hotkey(Key::Num1, true, true, false, false))
+        );
+        assert_eq!(
+            "a + CONTROL + option".parse(),
+            Ok(hotkey(Key::KeyA, true, false, true, false))
+        );
+        for meta in ["meta", "Super", "win", "Cmd", "command"] {
+            assert_eq!(
+                format!("{meta}+z").parse(),
+                Ok(hotkey(Key::KeyZ, false, false, false, true))
+            );
+        }
+        assert_eq!(
+            "Q".parse(),
+            Ok(hotkey(Key::KeyQ, false, false, false, false))
+        );
+    }
+
+    #[test]
+    fn function_keys_go_from_f1_to_f12() {
+        assert_eq!(parse_key("F1"), Some(Key::F1));
+        assert_eq!(parse_key("f9"), Some(Key::F9));
+        assert_eq!(parse_key("F12"), Some(Key::F12));
+        assert_eq!(parse_key("F0"), None);
+        assert_eq!(parse_key("F13"), None);
+        assert_eq!(parse_key("F"), Some(Key::KeyF));
+    }
+
+    #[test]
+    fn numpad_keys_go_from_0_to_9() {
+        assert_eq!(parse_key("Numpad0"), Some(Key::Kp0));
+        assert_eq!(parse_key("numpad9"), Some(Key::Kp9));
+        assert_eq!(parse_key("Numpad10"), None);
+        assert_eq!(parse_key("Numpad"), None);
+    }
+
+    #[test]
+    fn named_keys_are_parsed() {
+        assert_eq!(parse_key("Space"), Some(Key::Space));
+        assert_eq!(parse_key("esc"), Some(Key::Escape));
+        assert_eq!(parse_key("Return"), Some(Key::Return));
+        assert_eq!(parse_key("PageDown"), Some(Key::PageDown));
+        assert_eq!(parse_key("left"), Some(Key::LeftArrow));
+    }
+
+    #[test]
+    fn more_than_one_key_is_rejected() {
+        assert_eq!(
+            "Ctrl+A+B".parse::<Hotkey>(),
+            Err(String::from("'Ctrl+A+B' has more than one key"))
+        );
+        assert!("1+1".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn only_modifiers_are_rejected() {
+        assert_eq!(
+            "Ctrl+Shift".parse::<Hotkey>(),
+            Err(String::from(
+                "'Ctrl+Shift' needs a key that is not a modifier"
+            ))
+        );
+        assert!("".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert_eq!(
+            "Ctrl+Hyper".parse::<Hotkey>(),
+            Err(String::from("Unknown key 'Hyper'"))
+        );
+        assert!("Ctrl+!".parse::<Hotkey>().is_err());
+        assert!("Ctrl+".parse::<Hotkey>().is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use rotatar_types::{Config, ConfigFormat};
+
+use crate::Error;
+
+/// Parses a config written in the format. Errors point at the line and column the config is
+/// invalid at, whenever the format reports where that is.
+pub fn parse(text: &str, format: ConfigFormat) -> Result<Config, Error> {
+    let syntax = |location: Option<(usize, usize)>, message: String| Error::Syntax {
+        format,
+        location,
+        message,
+    };
+    match format {
+        ConfigFormat::Json => serde_json::from_str(text).map_err(|error| {
+            let location = (error.line(), error.column());
+            syntax(
+                Some(location).filter(|(line, _)| *line > 0),
+                without_location(error.to_string(), location),
+            )
+        }),
+        ConfigFormat::Toml => toml::from_str(text).map_err(|error| {
+            let location = error.span().map(|span| line_and_column(text, span.start));
+            syntax(location, error.message().trim_end().to_string())
+        }),
+        ConfigFormat::Yaml => serde_norway::from_str(text).map_err(|error| {
+            let location = error
+                .location()
+                .map(|location| (location.line(), location.column()));
+            let message = match location {
+                Some(location) => without_location(error.to_string(), location),
+                None => error.to_string(),
+            };
+            syntax(location, message)
+        }),
+        ConfigFormat::Ron => ron::from_str(text).map_err(|error| {
+            let location = (error.position.line, error.position.col);
+            syntax(Some(location), error.code.to_string())
+        }),
+    }
+}
+
+/// Gets the line and column of a byte in the text, both starting at 1.
+fn line_and_column(text: &str, byte: usize) -> (usize, usize) {
+    let before = text.get(..byte).unwrap_or(text);
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Removes the location that JSON and YAML errors end their messages with, as it is reported
+/// separately.
+fn without_location(message: String, (line, column): (usize, usize)) -> String {
+    message
+        .strip_suffix(&format!(" at line {line} column {column}"))
+        .map(String::from)
+        .unwrap_or(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config whose `sections` on the third line is not a pair of numbers, in each format.
+    const INVALID: [(ConfigFormat, &str); 4] = [
+        (
+            ConfigFormat::Json,
+            "{\n  \"idle_images\": [],\n  \"sections\": \"three\"\n}",
+        ),
+        (
+            ConfigFormat::Toml,
+            "idle_images = []\n\nsections = \"three\"\n",
+        ),
+        (ConfigFormat::Yaml, "idle_images: []\n\nsections: three\n"),
+        (
+            ConfigFormat::Ron,
+            "(\n  idle_images: [],\n  sections: \"three\",\n)",
+        ),
+    ];
+
+    fn syntax_error(text: &str, format: ConfigFormat) -> (Option<(usize, usize)>, String) {
+        match parse(text, format) {
+            Err(Error::Syntax {
+                format: reported,
+                location,
+                message,
+            }) => {
+                assert_eq!(reported, format);
+                (location, message)
+            }
+            other => panic!("Expected a syntax error, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn every_format_reports_where_the_config_is_invalid() {
+        for (format, text) in INVALID {
+            let (location, message) = syntax_error(text, format);
+            let (line, column) = location.unwrap_or_else(|| panic!("{format} has no location"));
+            assert_eq!(line, 3, "{format}: {message}");
+            assert!(column > 1, "{format}: {message}");
+        }
+    }
+
+    #[test]
+    fn messages_do_not_repeat_the_location() {
+        for (format, text) in INVALID {
+            let (_, message) = syntax_error(text, format);
+            assert!(!message.contains("line 3"), "{format}: {message}");
+            assert!(!message.is_empty(), "{format}");
+        }
+    }
+
+    #[test]
+    fn lines_and_columns_start_at_one() {
+        let text = "first\nsecond\n\nfourth";
+        assert_eq!(line_and_column(text, 0), (1, 1));
+        assert_eq!(line_and_column(text, 3), (1, 4));
+        assert_eq!(line_and_column(text, 6), (2, 1));
+        assert_eq!(line_and_column(text, 14), (4, 1));
+        assert_eq!(line_and_column(text, 16), (4, 3));
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let text = "é = [\n  \"ü\", x";
+        // `x` is the 12th byte of the second line, but the 8th character.
+        assert_eq!(line_and_column(text, text.find('x').unwrap()), (2, 8));
+        // Bytes past the end, or inside of a character, count from the end of the text.
+        assert_eq!(line_and_column(text, 1000), (2, 9));
+        assert_eq!(line_and_column(text, 1), (2, 9));
+    }
+
+    #[test]
+    fn only_the_reported_location_is_removed() {
+        assert_eq!(
+            without_location(String::from("expected value at line 2 column 5"), (2, 5)),
+            "expected value"
+        );
+        assert_eq!(
+            without_location(String::from("expected value at line 2 column 5"), (3, 1)),
+            "expected value at line 2 column 5"
+        );
+        assert_eq!(
+            without_location(String::from("at line 2 column 5 somewhere"), (2, 5)),
+            "at line 2 column 5 somewhere"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use async_channel::Receiver;
+use rotatar_types::{IdleAction, IdleActionsConfig};
+use tokio::time::Instant;
+
+/// How often an idle action checks whether it should be cut short.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Schedules idle actions at random intervals, returning a receiver that gets the index of an
+/// action when it starts and `None` when it ends. `quiet` is called to find out if the user has
+/// been quiet and the cursor still for long enough. Actions are skipped when they are due while
+/// that is not the case, and cut short as soon as it stops being the case.
+pub async fn get_idle_actions<F>(config: IdleActionsConfig, quiet: F) -> Receiver<Option<usize>>
+where
+    F: Fn() -> bool + Send + 'static,
+{
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let min_interval = config.min_interval().min(config.max_interval());
+        let max_interval = config.max_interval().max(min_interval);
+        loop {
+            let interval = fastrand::u64(min_interval..=max_interval);
+            tokio::time::sleep(Duration::from_millis(interval)).await;
+            if sender.is_closed() {
+                break;
+            }
+            let Some(index) = pick(config.actions()).filter(|_| quiet()) else {
+                continue;
+            };
+            if sender.send(Some(index)).await.is_err() {
+                break;
+            }
+            let until = Instant::now() + Duration::from_millis(config.actions()[index].duration());
+            while Instant::now() < until && quiet() {
+                tokio::time::sleep(
+                    IDLE_POLL_INTERVAL.min(until.saturating_duration_since(Instant::now())),
+                )
+                .await;
+            }
+            if sender.send(None).await.is_err() {
+                break;
+            }
+        }
+    });
+    out_receiver
+}
+
+/// Picks an action at random, where each action is as likely as its weight.
+fn pick(actions: &[IdleAction]) -> Option<usize> {
+    let total: f32 = actions.iter().map(|action| action.weight().max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut left = fastrand::f32() * total;
+    for (index, action) in actions.iter().enumerate() {
+        left -= action.weight().max(0.0);
+        if left < 0.0 {
+            return Some(index);
+        }
+    }
+    // Rounding can leave a tiny bit over, which belongs to the last action that can be picked.
+    actions.iter().rposition(|action| action.weight() > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn actions(weights: &[f32]) -> Vec<IdleAction> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| {
+                serde_json::from_value(json!({
+                    "name": format!("action {index}"),
+                    "images": ["idle.png"],
+                    "weight": weight,
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    /// Picks many times, counting how often each action was picked.
+    fn counts(weights: &[f32]) -> Vec<usize> {
+        fastrand::seed(7);
+        let actions = actions(weights);
+        let mut counts = vec![0; weights.len()];
+        for _ in 0..4000 {
+            counts[pick(&actions).unwrap()] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn nothing_is_picked_without_weight() {
+        assert_eq!(pick(&[]), None);
+        assert_eq!(pick(&actions(&[0.0, 0.0])), None);
+        assert_eq!(pick(&actions(&[-1.0, 0.0, -3.0])), None);
+    }
+
+    #[test]
+    fn actions_without_weight_are_never_picked() {
+        let counts = counts(&[0.0, 1.0, -2.0, 1.0]);
+        assert_eq!(counts[0], 0);
+        assert_eq!(counts[2], 0);
+        assert!(counts[1] > 0 && counts[3] > 0, "{counts:?}");
+    }
+
+    #[test]
+    fn negative_weights_do_not_take_from_the_others() {
+        let counts = counts(&[-5.0, 1.0]);
+        assert_eq!(counts, [0, 4000]);
+    }
+
+    #[test]
+    fn actions_are_picked_by_their_weight() {
+        let counts = counts(&[1.0, 3.0]);
+        let share = counts[1] as f32 / 4000.0;
+        assert!((0.7..0.8).contains(&share), "{counts:?}");
+    }
+}
@@ -0,0 +1,37 @@
+use std::{
+    sync::{Mutex, Once},
+    thread,
+};
+
+use rdev::EventType;
+
+type KeyCallback = Box<dyn FnMut(&EventType) -> bool + Send>;
+
+static CALLBACKS: Mutex<Vec<KeyCallback>> = Mutex::new(Vec::new());
+static LISTEN: Once = Once::new();
+
+/// Adds a callback to the global keyboard hook, starting the hook the first time this is called.
+/// Only one hook can exist per process, so everything that needs keyboard input shares it.
+///
+/// Callbacks run inside of the hook, so they decide what information ever leaves it. A callback
+/// returns whether it still wants keyboard input, and is removed once it doesn't.
+pub(crate) fn on_key_event<F>(callback: F)
+where
+    F: FnMut(&EventType) -> bool + Send + 'static,
+{
+    if let Ok(mut callbacks) = CALLBACKS.lock() {
+        callbacks.push(Box::new(callback));
+    }
+    LISTEN.call_once(|| {
+        thread::spawn(|| {
+            let result = rdev::listen(|event| {
+                if let Ok(mut callbacks) = CALLBACKS.lock() {
+                    callbacks.retain_mut(|callback| callback(&event.event_type));
+                }
+            });
+            if let Err(error) = result {
+                log::warn!("Could not listen for keyboard input: {error:?}");
+            }
+        });
+    });
+}
@@ -2,18 +2,60 @@ use std::time::Duration;
 use std::{fs::read_to_string, io};
 
 use async_channel::Receiver;
-use mouce::{Mouse, MouseActions};
-use rotatar_types::{Config, TwoInts};
-use rotatar_types::{FrontendError, ValidArgs};
+use mouce::{
+    Mouse, MouseActions,
+    common::{CallbackId, MouseButton, MouseEvent},
+};
+use rotatar_types::{Config, ImageSource, TrackingConfig, TrackingMode, TwoInts};
+use rotatar_types::{ConfigFormat, FrontendError, ValidArgs};
 use thiserror::Error;
+use tokio::task::JoinHandle;
 
+pub use afk::{AfkTimer, get_afk_status};
+pub use animation::{
+    Animation, AnimationFrame, AnimationFrames, Animations, DisplayedFrame, FrameImage,
+    get_animation_frames,
+};
+pub use blink::get_blinks;
+pub use expression::{Hotkey, get_expression_hotkeys, get_expression_reverts};
+pub use idle::get_idle_actions;
+pub use loops::{Frontend, run_loops};
 pub use message::Message;
+pub use motion::get_transforms;
+pub use prop::{get_prop_hotkeys, props_path, save_props};
+pub use reaction::{ClickButton, get_click_reactions};
+pub use spring::{SpringOffsets, get_spring_offsets};
 pub use state::State;
+pub use transition::{Transition, get_transitions};
+pub use typing::{TypingActivity, get_typing_activity};
+pub use watch::get_config_changes;
 
+mod afk;
+mod animation;
 pub mod audio;
+mod blink;
+mod discovery;
+pub mod effects;
+mod expression;
+mod format;
+mod idle;
+mod keyboard;
+mod loops;
 mod message;
+mod motion;
+mod paths;
+mod prop;
+mod reaction;
+mod spring;
+mod sprite_sheet;
 mod state;
+pub mod svg;
+#[cfg(test)]
+mod test_util;
+mod transition;
+mod typing;
 mod util;
+mod watch;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -27,36 +69,528 @@ pub enum Error {
     IO(#[from] io::Error),
     #[error("Could not parse config file")]
     Parse(#[from] serde_json::Error),
+    #[error("Could not parse the {format} config{}: {message}", at(location))]
+    Syntax {
+        format: ConfigFormat,
+        location: Option<(usize, usize)>,
+        message: String,
+    },
     #[error("Error from gui frontend")]
     Backend(#[from] FrontendError),
 }
 
 pub async fn run(args: &ValidArgs) -> Result<Config, Error> {
-    let config: Config = serde_json::from_str(&read_to_string(args.config_path())?)?;
-    if config.image_count() < config.total_sections() {
+    load(args)
+}
+
+/// Reads the config file and the assets it refers to, checking that the config is valid.
+pub(crate) fn load(args: &ValidArgs) -> Result<Config, Error> {
+    let mut config = format::parse(&read_to_string(args.config_path())?, args.config_format())?;
+    paths::resolve(&mut config, args.config_path())?;
+    config.set_window(config.window().with_args(args));
+    config.set_effects(config.effects().with_args(args));
+    if let Some(directory) = config.image_directory().cloned() {
+        if !config.idle_images().is_empty()
+            || !config.speaking_images().is_empty()
+            || config.idle_sheet().is_some()
+            || config.speaking_sheet().is_some()
+        {
+            return Err(Error::InvalidConfig(String::from(
+                "Idle and speaking images can either be found in an image directory, listed or sliced from a sprite sheet, not more than one",
+            )));
+        }
+        let sections = config.sections();
+        config.set_idle_images(discovery::discover(
+            directory.path(),
+            directory.idle(),
+            sections,
+        )?);
+        config.set_speaking_images(discovery::discover(
+            directory.path(),
+            directory.speaking(),
+            sections,
+        )?);
+    }
+    if let Some(sheet) = config.idle_sheet().cloned() {
+        if !config.idle_images().is_empty() {
+            return Err(Error::InvalidConfig(String::from(
+                "Idle images can either be listed or sliced from a sprite sheet, not both",
+            )));
+        }
+        config.set_idle_images(sprite_sheet::slice(&sheet)?);
+    }
+    if let Some(sheet) = config.speaking_sheet().cloned() {
+        if !config.speaking_images().is_empty() {
+            return Err(Error::InvalidConfig(String::from(
+                "Speaking images can either be listed or sliced from a sprite sheet, not both",
+            )));
+        }
+        config.set_speaking_images(sprite_sheet::slice(&sheet)?);
+    }
+    let (x_sections, y_sections) = config.sections();
+    if x_sections < 1 || y_sections < 1 {
+        return Err(Error::InvalidConfig(format!(
+            "You must have at least one section in each direction. You have {x_sections}x{y_sections} sections"
+        )));
+    }
+    let screen_size = config.screen_information().size();
+    if screen_size.x() < x_sections || screen_size.y() < y_sections {
+        return Err(Error::InvalidConfig(format!(
+            "The screen size ({}x{}) must be at least as large as the amount of sections ({x_sections}x{y_sections})",
+            screen_size.x(),
+            screen_size.y()
+        )));
+    }
+    let window = config.window();
+    if window.scale() <= 0.0 || window.padding() < 0.0 {
+        return Err(Error::InvalidConfig(format!(
+            "The avatar scale must be above 0 and the padding can't be negative. The scale is {} and the padding is {}",
+            window.scale(),
+            window.padding()
+        )));
+    }
+    let layered = !config.layers().is_empty()
+        && config.idle_images().is_empty()
+        && config.speaking_images().is_empty();
+    if !layered && config.image_count() < config.total_sections() {
         return Err(Error::InvalidConfig(format!(
             "You cannot have less images then you have sections. You only have {} images while you have {} sections",
             config.image_count(),
             config.total_sections()
         )));
     }
+    for layer in config.layers() {
+        if layer.images().is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "The '{}' layer needs at least one image",
+                layer.name()
+            )));
+        }
+        for (name, images) in [
+            ("", layer.images()),
+            ("speaking ", layer.speaking_images()),
+            ("blinking ", layer.blinking_images()),
+        ] {
+            if images.len() > 1 && images.len() < config.total_sections() {
+                return Err(Error::InvalidConfig(format!(
+                    "The '{}' layer needs either one {name}image, or one {name}image for each of the {} sections. It has {} {name}images",
+                    layer.name(),
+                    config.total_sections(),
+                    images.len()
+                )));
+            }
+        }
+    }
+    for path in config.image_paths() {
+        if svg::is_svg(path) {
+            svg::size(path).map_err(Error::InvalidConfig)?;
+        }
+    }
+    if let Some(source) = config.image_sources().into_iter().find(
+        |source| matches!(source, ImageSource::Sequence(sequence) if sequence.frames().is_empty()),
+    ) {
+        return Err(Error::InvalidConfig(format!(
+            "Frame sequences need at least one frame, found {source:?}"
+        )));
+    }
+    if config
+        .afk()
+        .is_some_and(|afk| afk.sleeping_images().is_empty())
+    {
+        return Err(Error::InvalidConfig(String::from(
+            "AFK detection needs at least one sleeping image",
+        )));
+    }
+    if let Some(click_reactions) = config.click_reactions() {
+        for (name, reaction) in [
+            ("left click", click_reactions.left()),
+            ("right click", click_reactions.right()),
+        ] {
+            let Some(reaction) = reaction else {
+                continue;
+            };
+            if reaction.images().len() != 1 && reaction.images().len() < config.total_sections() {
+                return Err(Error::InvalidConfig(format!(
+                    "The {name} reaction needs either one image, or one image for each of the {} sections. It has {} images",
+                    config.total_sections(),
+                    reaction.images().len()
+                )));
+            }
+        }
+    }
+    if let Some(idle_actions) = config.idle_actions() {
+        if !idle_actions
+            .actions()
+            .iter()
+            .any(|action| action.weight() > 0.0)
+        {
+            return Err(Error::InvalidConfig(String::from(
+                "Idle actions need at least one action with a weight above 0",
+            )));
+        }
+        for action in idle_actions.actions() {
+            if action.images().len() != 1 && action.images().len() < config.total_sections() {
+                return Err(Error::InvalidConfig(format!(
+                    "The '{}' idle action needs either one image, or one image for each of the {} sections. It has {} images",
+                    action.name(),
+                    config.total_sections(),
+                    action.images().len()
+                )));
+            }
+        }
+    }
+    // Image variants that replace the normal images for each section.
+    let mut variants = Vec::new();
+    if let Some(typing) = config.typing() {
+        variants.push((String::from("typing idle"), typing.idle_images()));
+        variants.push((String::from("typing speaking"), typing.speaking_images()));
+        variants.push((
+            String::from("typing blinking idle"),
+            typing.blinking_idle_images(),
+        ));
+        variants.push((
+            String::from("typing blinking speaking"),
+            typing.blinking_speaking_images(),
+        ));
+    }
+    if let Some(blink) = config.blink() {
+        if blink.idle_images().is_empty()
+            && config
+                .layers()
+                .iter()
+                .all(|layer| layer.blinking_images().is_empty())
+        {
+            return Err(Error::InvalidConfig(String::from(
+                "Blinking needs eyes closed idle images, or a layer with blinking images",
+            )));
+        }
+        variants.push((String::from("blink idle"), blink.idle_images()));
+        variants.push((String::from("blink speaking"), blink.speaking_images()));
+    }
+    for (name, expression) in config.expressions() {
+        if let Some(Err(error)) = expression.hotkey().map(str::parse::<Hotkey>) {
+            return Err(Error::InvalidConfig(format!(
+                "The hotkey of the '{name}' expression is invalid: {error}"
+            )));
+        }
+        if expression.idle_images().is_empty() && expression.layers().is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "The '{name}' expression needs idle images, or images for at least one layer"
+            )));
+        }
+        for (layer, images) in expression.layers() {
+            if config
+                .layers()
+                .iter()
+                .all(|existing| existing.name() != layer)
+            {
+                return Err(Error::InvalidConfig(format!(
+                    "The '{name}' expression replaces the '{layer}' layer, which does not exist"
+                )));
+            }
+            if images.len() != 1 && images.len() < config.total_sections() {
+                return Err(Error::InvalidConfig(format!(
+                    "The '{name}' expression needs either one image, or one image for each of the {} sections for the '{layer}' layer. It has {} images",
+                    config.total_sections(),
+                    images.len()
+                )));
+            }
+        }
+        variants.push((format!("'{name}' idle"), expression.idle_images()));
+        variants.push((format!("'{name}' speaking"), expression.speaking_images()));
+    }
+    let springs = config
+        .layers()
+        .iter()
+        .filter_map(|layer| Some((format!("'{}' layer", layer.name()), layer.spring()?)))
+        .chain(
+            config
+                .props()
+                .iter()
+                .filter_map(|prop| Some((format!("'{}' prop", prop.name()), prop.spring()?))),
+        );
+    for (name, spring) in springs {
+        if spring.stiffness() <= 0.0
+            || spring.stiffness() > spring::MAX_STIFFNESS
+            || spring.damping() < 0.0
+            || spring.max_offset() < 0.0
+        {
+            return Err(Error::InvalidConfig(format!(
+                "The spring of the {name} needs a stiffness above 0 and at most {}, and can't have a negative damping or maximum offset. The stiffness is {}, the damping is {} and the maximum offset is {}",
+                spring::MAX_STIFFNESS,
+                spring.stiffness(),
+                spring.damping(),
+                spring.max_offset()
+            )));
+        }
+    }
+    for (index, prop) in config.props().iter().enumerate() {
+        let name = prop.name();
+        if config.props()[..index]
+            .iter()
+            .any(|other| other.name() == name)
+        {
+            return Err(Error::InvalidConfig(format!(
+                "There is more than one prop called '{name}'"
+            )));
+        }
+        if let Some(Err(error)) = prop.hotkey().map(str::parse::<Hotkey>) {
+            return Err(Error::InvalidConfig(format!(
+                "The hotkey of the '{name}' prop is invalid: {error}"
+            )));
+        }
+        if prop.offsets().len() > 1 && prop.offsets().len() < config.total_sections() {
+            return Err(Error::InvalidConfig(format!(
+                "The '{name}' prop needs either one offset, or one offset for each of the {} sections. It has {} offsets",
+                config.total_sections(),
+                prop.offsets().len()
+            )));
+        }
+    }
+    for (name, images) in variants {
+        if !images.is_empty() && images.len() < config.total_sections() {
+            return Err(Error::InvalidConfig(format!(
+                "You cannot have less {name} images then you have sections. You only have {} {name} images while you have {} sections",
+                images.len(),
+                config.total_sections()
+            )));
+        }
+    }
 
     Ok(config)
 }
 
-pub async fn get_mouse_pos(interval: Duration, modifiers: TwoInts) -> Receiver<TwoInts> {
+/// Describes where in a config file an error is, for error messages.
+fn at(location: &Option<(usize, usize)>) -> String {
+    location
+        .map(|(line, column)| format!(" at line {line}, column {column}"))
+        .unwrap_or_default()
+}
+
+/// Tracks the cursor, returning a receiver that gets the cursor position whenever it changes.
+///
+/// Depending on the tracking mode, the position is either polled at the configured interval, or
+/// only read when the mouse reports movement. If listening for movement fails, this falls back to
+/// polling.
+pub async fn get_mouse_pos(tracking: TrackingConfig, modifiers: TwoInts) -> Receiver<TwoInts> {
+    track_mouse(tracking, modifiers).0
+}
+
+/// Tracks the cursor like [get_mouse_pos], also returning the task that does so. Aborting the task
+/// unhooks it from the mouse right away, instead of once the mouse next moves.
+pub(crate) fn track_mouse(
+    tracking: TrackingConfig,
+    modifiers: TwoInts,
+) -> (Receiver<TwoInts>, JoinHandle<()>) {
     let (sender, out_receiver) = async_channel::unbounded();
 
-    tokio::spawn(async move {
+    let task = tokio::spawn(async move {
         let mouse = Mouse::new();
-        interval!(interval, {
-            if let Ok(position) = mouse.get_position() {
-                sender
-                    .send(TwoInts::from(position) + modifiers)
-                    .await
-                    .unwrap();
-            }
+        let mut hook = None;
+        let mut last_position = None;
+        let source = movement_source(tracking, || {
+            let (movement, movement_hook) = hook_movement()?;
+            hook = Some(movement_hook);
+            Some(movement)
         });
+        match source {
+            MovementSource::Events(movement) => {
+                while movement.recv().await.is_ok() {
+                    let Some(position) = read_position(&mouse, modifiers, &mut last_position)
+                    else {
+                        continue;
+                    };
+                    if sender.send(position).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            MovementSource::Poll(poll_interval) => {
+                interval!(poll_interval, sender, {
+                    let Some(position) = read_position(&mouse, modifiers, &mut last_position)
+                    else {
+                        continue;
+                    };
+                    if sender.send(position).await.is_err() {
+                        break;
+                    }
+                });
+            }
+        }
+        drop(hook);
+    });
+    (out_receiver, task)
+}
+
+/// Where [get_mouse_pos] learns that the cursor may have moved.
+#[derive(Debug)]
+enum MovementSource {
+    /// The mouse reports each movement.
+    Events(Receiver<()>),
+    /// The cursor position is read every interval.
+    Poll(Duration),
+}
+
+/// Picks where mouse movement comes from, only hooking into the mouse in [TrackingMode::Event].
+/// Polling is the fallback when hooking fails.
+fn movement_source(
+    tracking: TrackingConfig,
+    hook: impl FnOnce() -> Option<Receiver<()>>,
+) -> MovementSource {
+    let movement = match tracking.mode() {
+        TrackingMode::Event => hook(),
+        TrackingMode::Poll => None,
+    };
+    match movement {
+        Some(movement) => MovementSource::Events(movement),
+        None => MovementSource::Poll(Duration::from_millis(tracking.poll_interval().max(1))),
+    }
+}
+
+/// A callback hooked into the mouse, which is unhooked again once this is dropped. Tasks that hook
+/// into the mouse hold on to this, so that their hook is gone as soon as they stop or are aborted.
+struct MouseHook {
+    mouse: Mouse,
+    id: CallbackId,
+}
+
+impl MouseHook {
+    fn new(callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<Self, mouce::error::Error> {
+        let mut mouse = Mouse::new();
+        let id = mouse.hook(callback)?;
+        Ok(Self { mouse, id })
+    }
+}
+
+impl Drop for MouseHook {
+    fn drop(&mut self) {
+        if let Err(error) = self.mouse.unhook(self.id) {
+            log::warn!("Could not stop listening to the mouse: {error:?}");
+        }
+    }
+}
+
+/// Hooks into mouse movement, returning a receiver that is notified whenever the mouse moves.
+/// Movement that happens while a notification is still waiting is merged into that notification.
+fn hook_movement() -> Option<(Receiver<()>, MouseHook)> {
+    let (sender, receiver) = async_channel::bounded(1);
+    let hook = MouseHook::new(Box::new(move |event| {
+        if let MouseEvent::RelativeMove(..) | MouseEvent::AbsoluteMove(..) = event {
+            let _ = sender.try_send(());
+        }
+    }));
+    match hook {
+        Ok(hook) => Some((receiver, hook)),
+        Err(error) => {
+            log::warn!("Could not listen for mouse movement, falling back to polling: {error:?}");
+            None
+        }
+    }
+}
+
+/// Reads the cursor position, only returning it if it is different from the last position read.
+fn read_position(
+    mouse: &Mouse,
+    modifiers: TwoInts,
+    last_position: &mut Option<TwoInts>,
+) -> Option<TwoInts> {
+    let position = TwoInts::from(mouse.get_position().ok()?) + modifiers;
+    deduplicate(position, last_position)
+}
+
+/// Only returns the position if it is different from the last one, which it then becomes.
+fn deduplicate(position: TwoInts, last_position: &mut Option<TwoInts>) -> Option<TwoInts> {
+    if *last_position == Some(position) {
+        None
+    } else {
+        *last_position = Some(position);
+        Some(position)
+    }
+}
+
+/// Listens for left and right mouse button presses, returning a receiver that gets each button as
+/// it is pressed.
+pub async fn get_mouse_clicks() -> Receiver<ClickButton> {
+    listen_for_clicks().0
+}
+
+/// Listens for mouse clicks like [get_mouse_clicks], also returning the task that does so.
+/// Aborting the task unhooks it from the mouse right away.
+pub(crate) fn listen_for_clicks() -> (Receiver<ClickButton>, JoinHandle<()>) {
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    let task = tokio::spawn(async move {
+        let hook_sender = sender.clone();
+        let hook = MouseHook::new(Box::new(move |event| {
+            let button = match event {
+                MouseEvent::Press(MouseButton::Left) => ClickButton::Left,
+                MouseEvent::Press(MouseButton::Right) => ClickButton::Right,
+                _ => return,
+            };
+            let _ = hook_sender.send_blocking(button);
+        }));
+        let hook = match hook {
+            Ok(hook) => hook,
+            Err(error) => {
+                log::warn!("Could not listen for mouse clicks: {error:?}");
+                return;
+            }
+        };
+        // The hook is removed once it is dropped, so keep it around until nobody is listening.
+        while !sender.is_closed() {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        drop(hook);
     });
-    out_receiver
+    (out_receiver, task)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn tracking(mode: &str) -> TrackingConfig {
+        serde_json::from_value(json!({ "mode": mode, "poll_interval": 20 })).unwrap()
+    }
+
+    #[test]
+    fn tracking_listens_for_movement_by_default() {
+        assert_eq!(TrackingConfig::default().mode(), TrackingMode::Event);
+        let tracking: TrackingConfig = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(tracking.mode(), TrackingMode::Event);
+    }
+
+    #[test]
+    fn event_mode_uses_the_movement_hook() {
+        let (_sender, receiver) = async_channel::bounded(1);
+        let source = movement_source(tracking("event"), || Some(receiver));
+        assert!(matches!(source, MovementSource::Events(_)));
+    }
+
+    #[test]
+    fn event_mode_falls_back_to_polling() {
+        let source = movement_source(tracking("event"), || None);
+        assert!(matches!(source, MovementSource::Poll(interval) if interval.as_millis() == 20));
+    }
+
+    #[test]
+    fn poll_mode_never_hooks_the_mouse() {
+        let source = movement_source(tracking("poll"), || panic!("the mouse was hooked"));
+        assert!(matches!(source, MovementSource::Poll(interval) if interval.as_millis() == 20));
+    }
+
+    #[test]
+    fn identical_positions_are_only_read_once() {
+        let mut last_position = None;
+        let here = TwoInts::new(10, 20);
+        let there = TwoInts::new(11, 20);
+
+        assert_eq!(deduplicate(here, &mut last_position), Some(here));
+        assert_eq!(deduplicate(here, &mut last_position), None);
+        assert_eq!(deduplicate(there, &mut last_position), Some(there));
+        assert_eq!(deduplicate(here, &mut last_position), Some(here));
+        assert_eq!(deduplicate(here, &mut last_position), None);
+    }
 }
@@ -0,0 +1,349 @@
+use std::{
+    env,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use async_channel::{Receiver, Sender};
+use rotatar_types::{Config, LayerConfig, PropConfig, TransitionKind, ValidArgs};
+use tokio::task::JoinHandle;
+
+use crate::{
+    Animations, Error, Message, State, audio::AudioMessage, get_afk_status, get_animation_frames,
+    get_blinks, get_config_changes, get_expression_hotkeys, get_expression_reverts,
+    get_idle_actions, get_prop_hotkeys, get_spring_offsets, get_transforms, get_transitions,
+    get_typing_activity, reaction, track_mouse,
+};
+
+/// What the loops of the avatar need from a frontend.
+pub trait Frontend: Clone + Send + Sync + 'static {
+    /// Whatever the frontend draws the images of the avatar from.
+    type Images: Send + 'static;
+
+    fn state(&self) -> &Mutex<State>;
+
+    fn config(&self) -> &Mutex<Config>;
+
+    /// Gets the images of a reloaded config ready to be drawn. This runs on a blocking thread, so
+    /// it may take its time.
+    fn load_images(&self, config: &Config, animations: Arc<Animations>) -> Self::Images;
+
+    /// Starts drawing the avatar from the images of a reloaded config. This is called while the
+    /// state is locked, so it should only swap the images in.
+    fn swap_images(&self, images: Self::Images);
+}
+
+/// Runs every part of the avatar that lives in its own loop, like blinking, hotkeys and following
+/// the cursor. Each loop sets what it finds on the state, and then sends the message for it.
+///
+/// The config is watched for changes as well. A changed config and its images are loaded on a
+/// blocking thread, then swapped in together with the state, and the loops start over with the new
+/// config before [Message::ConfigChanged] is sent.
+pub async fn run_loops<F: Frontend>(
+    frontend: F,
+    args: ValidArgs,
+    animations: Arc<Animations>,
+    sender: Sender<Message>,
+) {
+    let Ok(config) = frontend.config().lock().map(|config| config.clone()) else {
+        return;
+    };
+    let config_changes = get_config_changes(args, &config).await;
+    let mut loops = Loops::start(&frontend, &config, animations, &sender);
+
+    while let Ok(config) = config_changes.recv().await {
+        let loading = frontend.clone();
+        let loaded = tokio::task::spawn_blocking(move || {
+            let animations = Arc::new(Animations::load(&config)?);
+            let images = loading.load_images(&config, animations.clone());
+            Ok::<_, Error>((config, animations, images))
+        })
+        .await;
+        let (config, animations, images) = match loaded {
+            Ok(Ok(loaded)) => loaded,
+            Ok(Err(error)) => {
+                log::warn!("The changed config could not be loaded: {error}");
+                continue;
+            }
+            Err(error) => {
+                log::warn!("The changed config could not be loaded: {error}");
+                continue;
+            }
+        };
+
+        // The old loops are stopped first, so that they can't set anything on the new state.
+        drop(loops);
+        if let Ok(mut state) = frontend.state().lock() {
+            frontend.swap_images(images);
+            state.reload(&config);
+            let _ = state
+                .audio_handler_sender()
+                .try_send(AudioMessage::SetConfig(config.audio()));
+            if let Ok(mut current) = frontend.config().lock() {
+                *current = config.clone();
+            }
+        }
+        loops = Loops::start(&frontend, &config, animations, &sender);
+        if sender
+            .send(Message::ConfigChanged(Box::new(config)))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// The running loops of the avatar, which are all stopped when this is dropped.
+struct Loops<F> {
+    frontend: F,
+    sender: Sender<Message>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<F: Frontend> Loops<F> {
+    /// Starts the loops that the config turns on.
+    fn start(
+        frontend: &F,
+        config: &Config,
+        animations: Arc<Animations>,
+        sender: &Sender<Message>,
+    ) -> Self {
+        let mut loops = Self {
+            frontend: frontend.clone(),
+            sender: sender.clone(),
+            tasks: Vec::new(),
+        };
+
+        let afk_timer = frontend
+            .state()
+            .lock()
+            .map(|state| state.afk_timer())
+            .unwrap_or_default();
+        if let Some(typing) = config.typing().cloned() {
+            loops.forward(
+                get_typing_activity(typing, afk_timer.clone()),
+                State::set_typing,
+                Message::TypingChanged,
+            );
+        }
+
+        if let Some(afk) = config.afk().cloned() {
+            loops.forward(
+                get_afk_status(afk_timer, afk),
+                State::set_sleeping,
+                Message::SleepingChanged,
+            );
+        }
+
+        if let Some(idle_actions) = config.idle_actions().cloned() {
+            let quiet_time = idle_actions.quiet_time();
+            let frontend = frontend.clone();
+            loops.forward(
+                get_idle_actions(idle_actions, move || {
+                    frontend
+                        .state()
+                        .lock()
+                        .is_ok_and(|state| state.is_quiet(quiet_time))
+                }),
+                State::set_idle_action,
+                Message::IdleActionChanged,
+            );
+        }
+
+        if let Some(click_reactions) = config.click_reactions().cloned() {
+            let (reactions, listener) = reaction::click_reactions(click_reactions);
+            loops.tasks.push(listener);
+            loops.forward(
+                async move { reactions },
+                State::set_reaction,
+                Message::ReactionChanged,
+            );
+        }
+
+        if let Some(blink) = config.blink().cloned() {
+            loops.forward(
+                get_blinks(blink),
+                State::set_blinking,
+                Message::BlinkingChanged,
+            );
+        }
+
+        if let Some(motion) = config.motion() {
+            let frontend = frontend.clone();
+            loops.forward(
+                get_transforms(motion, move || {
+                    let state = frontend.state().lock().ok()?;
+                    let config = frontend.config().lock().ok()?;
+                    Some(state.motion_level(&config))
+                }),
+                State::set_transform,
+                Message::TransformChanged,
+            );
+        }
+
+        let layer_springs: Vec<_> = config.layers().iter().map(LayerConfig::spring).collect();
+        let prop_springs: Vec<_> = config.props().iter().map(PropConfig::spring).collect();
+        if layer_springs
+            .iter()
+            .chain(&prop_springs)
+            .any(Option::is_some)
+        {
+            let frontend = frontend.clone();
+            loops.forward(
+                get_spring_offsets(layer_springs, prop_springs, move || {
+                    Some(frontend.state().lock().ok()?.spring_anchor())
+                }),
+                State::set_spring_offsets,
+                Message::SpringsChanged,
+            );
+        }
+
+        let props = config.props().clone();
+        if props.iter().any(|prop| prop.hotkey().is_some()) {
+            let frontend = frontend.clone();
+            loops.spawn(async move { get_prop_hotkeys(&props).await }, move |name| {
+                // The hotkey of a prop shows it when it is hidden, and hides it when it is shown.
+                let shown = frontend
+                    .state()
+                    .lock()
+                    .is_ok_and(|state| state.props().contains(&name));
+                Some(Message::SetProp(name, !shown))
+            });
+        }
+
+        let expressions = config.expressions().clone();
+        if expressions
+            .values()
+            .any(|expression| expression.timeout().is_some())
+        {
+            let frontend = frontend.clone();
+            loops.spawn(
+                get_expression_reverts(move || {
+                    let (Ok(state), Ok(config)) =
+                        (frontend.state().lock(), frontend.config().lock())
+                    else {
+                        return false;
+                    };
+                    state.is_expression_expired(&config)
+                }),
+                |()| Some(Message::SetExpression(None)),
+            );
+        }
+        if !expressions.is_empty() {
+            let frontend = frontend.clone();
+            loops.spawn(
+                async move { get_expression_hotkeys(&expressions).await },
+                move |name| {
+                    // Pressing the hotkey of the active expression switches back to the default.
+                    let active = frontend
+                        .state()
+                        .lock()
+                        .is_ok_and(|state| state.expression() == Some(&name));
+                    Some(Message::SetExpression((!active).then_some(name)))
+                },
+            );
+        }
+
+        let transition = config.transition();
+        if transition.kind() != TransitionKind::Cut {
+            let frontend = frontend.clone();
+            loops.forward(
+                get_transitions(transition, move || {
+                    let state = frontend.state().lock().ok()?;
+                    let config = frontend.config().lock().ok()?;
+                    state.current_image_source(&config).cloned()
+                }),
+                State::set_transition,
+                Message::TransitionChanged,
+            );
+        }
+
+        if !animations.is_empty() {
+            let frontend = frontend.clone();
+            loops.forward(
+                get_animation_frames(animations, move || {
+                    let (Ok(state), Ok(config)) =
+                        (frontend.state().lock(), frontend.config().lock())
+                    else {
+                        return Vec::new();
+                    };
+                    state.shown_image_sources(&config)
+                }),
+                State::set_frames,
+                Message::FramesChanged,
+            );
+        }
+
+        let tracking = config.tracking();
+        let modifiers = config.screen_information().modifier(env::consts::OS);
+        // Blended sections change with every movement, not only between sections.
+        let blend_sections = config.transition().blend_sections();
+        let moved_frontend = frontend.clone();
+        let (positions, tracker) = track_mouse(tracking, modifiers);
+        loops.tasks.push(tracker);
+        loops.spawn(async move { positions }, move |position| {
+            moved_frontend
+                .state()
+                .lock()
+                .is_ok_and(|mut state| {
+                    state.set_current_image_xy(position.x(), position.y()) || blend_sections
+                })
+                .then_some(Message::CurrentImageChanged)
+        });
+
+        loops
+    }
+
+    /// Sets everything a loop sends on the state, and then sends the message made from it.
+    fn forward<T>(
+        &mut self,
+        receiver: impl Future<Output = Receiver<T>> + Send + 'static,
+        set: fn(&mut State, T),
+        message: fn(T) -> Message,
+    ) where
+        T: Clone + Send + 'static,
+    {
+        let frontend = self.frontend.clone();
+        self.spawn(receiver, move |value: T| {
+            if let Ok(mut state) = frontend.state().lock() {
+                set(&mut state, value.clone());
+            }
+            Some(message(value))
+        });
+    }
+
+    /// Sends the message made from everything a loop sends, if there is one.
+    fn spawn<T, M>(
+        &mut self,
+        receiver: impl Future<Output = Receiver<T>> + Send + 'static,
+        mut message: M,
+    ) where
+        T: Send + 'static,
+        M: FnMut(T) -> Option<Message> + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        self.tasks.push(tokio::spawn(async move {
+            let receiver = receiver.await;
+            while let Ok(value) = receiver.recv().await {
+                let Some(message) = message(value) else {
+                    continue;
+                };
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+}
+
+impl<F> Drop for Loops<F> {
+    fn drop(&mut self) {
+        // The loops that feed these tasks stop once nobody is listening to them anymore. The ones
+        // that hook into the mouse are among these tasks, so that their hooks are removed right
+        // away instead of once they next notice.
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use std::sync::Arc;
 
 use async_channel::{Receiver, Sender};
-use rotatar_types::Config;
+use rotatar_types::{Config, Transform};
 
-use crate::audio::{AudioHandler, AudioStatus};
+use crate::{
+    AnimationFrames, ClickButton, SpringOffsets, Transition, TypingActivity,
+    audio::{AudioHandler, AudioStatus},
+};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -13,6 +16,25 @@ pub enum Message {
     CurrentImageChanged,
     SensitivityChanged(f32),
     MagnitudeChanged(i32),
-    ConfigChanged(Config),
+    /// The config was reloaded, and has already been swapped in together with the state. It is
+    /// boxed, as it is much larger than every other message.
+    ConfigChanged(Box<Config>),
     AudioDevicesChanged(Vec<String>),
+    TypingChanged(TypingActivity),
+    SleepingChanged(Option<usize>),
+    ReactionChanged(Option<ClickButton>),
+    IdleActionChanged(Option<usize>),
+    FramesChanged(AnimationFrames),
+    BlinkingChanged(bool),
+    TransformChanged(Transform),
+    SpringsChanged(SpringOffsets),
+    TransitionChanged(Option<Transition>),
+    /// Switches to the named expression, or back to the default images.
+    SetExpression(Option<String>),
+    /// Shows or hides the named prop.
+    SetProp(String, bool),
+    /// The window was resized to the width and height, in logical pixels.
+    WindowResized(f32, f32),
+    /// The window moved to a display with a different scale factor.
+    ScaleFactorChanged(f32),
 }
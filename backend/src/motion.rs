@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+use async_channel::Receiver;
+use rotatar_types::{MotionConfig, Transform};
+
+use crate::interval;
+
+/// How often the transform is recalculated.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Changes smaller than this are not sent, so a resting avatar does not cause redraws.
+const MIN_CHANGE: f32 = 0.001;
+
+/// Calculates the avatar's transform every frame, returning a receiver that gets the transform
+/// whenever it changes. `level` is called to get the current volume, between 0 and 1.
+pub async fn get_transforms<F>(config: MotionConfig, level: F) -> Receiver<Transform>
+where
+    F: Fn() -> Option<f32> + Send + 'static,
+{
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let mut current = config.idle();
+        if sender.send(current).await.is_err() {
+            return;
+        }
+        let mut last_frame = Instant::now();
+        interval!(FRAME_INTERVAL, sender, {
+            let delta = last_frame.elapsed().as_secs_f32();
+            last_frame = Instant::now();
+            let target = config.transform_at(level().unwrap_or_default());
+            let next = if config.smoothing() == 0 {
+                target
+            } else {
+                let amount = 1.0 - (-delta * 1000.0 / config.smoothing() as f32).exp();
+                current.lerp(&target, amount)
+            };
+            if changed(&current, &next) {
+                current = next;
+                if sender.send(current).await.is_err() {
+                    break;
+                }
+            }
+        });
+    });
+    out_receiver
+}
+
+fn changed(from: &Transform, to: &Transform) -> bool {
+    (from.offset() - to.offset()).abs() > MIN_CHANGE
+        || (from.scale_x() - to.scale_x()).abs() > MIN_CHANGE
+        || (from.scale_y() - to.scale_y()).abs() > MIN_CHANGE
+        || (from.rotation() - to.rotation()).abs() > MIN_CHANGE
+}
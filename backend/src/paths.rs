@@ -0,0 +1,155 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rotatar_types::Config;
+
+use crate::Error;
+
+/// Resolves every asset path in the config, so that the avatar can be started from anywhere.
+///
+/// `~` and environment variables like `$HOME` or `${HOME}` are expanded first. Relative paths are
+/// then taken to be relative to the directory the config file is in. Every asset that can't be
+/// found is listed in the error, instead of failing on the first one when it is shown.
+pub fn resolve(config: &mut Config, config_path: &Path) -> Result<(), Error> {
+    let base = config_path.parent().unwrap_or(Path::new(""));
+    let mut missing: Vec<String> = Vec::new();
+    for path in config.asset_paths_mut() {
+        let written = path.clone();
+        let expanded = match written.to_str().map(shellexpand::full) {
+            Some(Ok(expanded)) => PathBuf::from(expanded.as_ref()),
+            Some(Err(error)) => {
+                return Err(Error::InvalidConfig(format!(
+                    "Could not expand the path '{}': {error}",
+                    written.display()
+                )));
+            }
+            None => written.clone(),
+        };
+        let joined = base.join(expanded);
+        match fs::canonicalize(&joined) {
+            Ok(resolved) => *path = resolved,
+            Err(_) => {
+                let entry = format!(
+                    "'{}' (looked for '{}')",
+                    written.display(),
+                    joined.display()
+                );
+                if !missing.contains(&entry) {
+                    missing.push(entry);
+                }
+            }
+        }
+    }
+    if !missing.is_empty() {
+        return Err(Error::InvalidConfig(format!(
+            "Could not find {} of the assets the config uses: {}",
+            missing.len(),
+            missing.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rotatar_types::ImageSource;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::{self, directory, invalid_config_message};
+
+    fn config(idle: &[&str], speaking: &[&str]) -> Config {
+        test_util::config(json!({ "idle_images": idle, "speaking_images": speaking }))
+    }
+
+    fn paths(images: &[ImageSource]) -> Vec<PathBuf> {
+        images
+            .iter()
+            .map(|image| match image {
+                ImageSource::Path(path) => path.clone(),
+                other => panic!("Expected a path, found {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn relative_paths_are_relative_to_the_config() {
+        let files = directory(&["images/idle.png", "speaking.png"]);
+        let config_path = files.path().join("config.json");
+        let directory = fs::canonicalize(files.path()).unwrap();
+        let mut config = config(&["images/idle.png"], &["./images/../speaking.png"]);
+        resolve(&mut config, &config_path).unwrap();
+        assert_eq!(
+            paths(config.idle_images()),
+            [directory.join("images/idle.png")]
+        );
+        assert_eq!(
+            paths(config.speaking_images()),
+            [directory.join("speaking.png")]
+        );
+    }
+
+    #[test]
+    fn absolute_paths_are_kept() {
+        let directory = directory(&["idle.png"]);
+        let idle = fs::canonicalize(directory.path().join("idle.png")).unwrap();
+        let mut config = config(&[idle.to_str().unwrap()], &[]);
+        resolve(&mut config, Path::new("elsewhere/config.json")).unwrap();
+        assert_eq!(paths(config.idle_images()), [idle]);
+    }
+
+    #[test]
+    fn environment_variables_are_expanded() {
+        // Cargo sets this while running tests.
+        let manifest = fs::canonicalize(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let mut config = config(
+            &["$CARGO_MANIFEST_DIR/Cargo.toml"],
+            &["${CARGO_MANIFEST_DIR}/src/paths.rs"],
+        );
+        resolve(&mut config, Path::new("elsewhere/config.json")).unwrap();
+        assert_eq!(paths(config.idle_images()), [manifest.join("Cargo.toml")]);
+        assert_eq!(
+            paths(config.speaking_images()),
+            [manifest.join("src/paths.rs")]
+        );
+    }
+
+    #[test]
+    fn the_home_directory_is_expanded() {
+        let home = shellexpand::tilde("~").into_owned();
+        let mut config = config(&["~/rotatar-missing-image.png"], &[]);
+        let message = invalid_config_message(resolve(&mut config, Path::new("config.json")));
+        let expanded = Path::new(&home).join("rotatar-missing-image.png");
+        assert!(
+            message.contains(&format!("looked for '{}'", expanded.display())),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn unset_variables_are_reported() {
+        let mut config = config(&["$ROTATAR_SURELY_UNSET_VARIABLE/idle.png"], &[]);
+        let message = invalid_config_message(resolve(&mut config, Path::new("config.json")));
+        assert!(message.contains("Could not expand the path"), "{message}");
+    }
+
+    #[test]
+    fn each_missing_file_is_listed_once() {
+        let directory = directory(&["found.png"]);
+        let config_path = directory.path().join("config.json");
+        let mut config = config(
+            &["found.png", "missing.png", "other.png"],
+            &["missing.png", "found.png"],
+        );
+        let message = invalid_config_message(resolve(&mut config, &config_path));
+        assert!(
+            message.contains("Could not find 2 of the assets"),
+            "{message}"
+        );
+        assert_eq!(message.matches("'missing.png'").count(), 1, "{message}");
+        assert_eq!(message.matches("'other.png'").count(), 1, "{message}");
+        assert!(!message.contains("'found.png'"), "{message}");
+    }
+}
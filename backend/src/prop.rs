@@ -0,0 +1,64 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use async_channel::Receiver;
+use rotatar_types::PropConfig;
+
+use crate::{Error, Hotkey, expression::listen_for_hotkeys};
+
+/// Gets the file the shown props are remembered in, which sits next to the config file.
+pub fn props_path(config_path: &Path) -> PathBuf {
+    let stem = config_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    config_path.with_file_name(format!("{stem}.props.json"))
+}
+
+/// Reads the names of the props that were shown when they were last toggled. Props that were
+/// never toggled, because the file doesn't exist yet, are shown if they are shown by default.
+/// Names of props that are no longer in the config are left out.
+pub(crate) fn load_props(path: &Path, props: &[PropConfig]) -> Vec<String> {
+    let saved = fs::read_to_string(path)
+        .ok()
+        .and_then(|saved| serde_json::from_str::<Vec<String>>(&saved).ok());
+    props
+        .iter()
+        .filter(|prop| match &saved {
+            Some(saved) => saved.iter().any(|name| name == prop.name()),
+            None => prop.shown(),
+        })
+        .map(|prop| prop.name().to_string())
+        .collect()
+}
+
+/// Remembers which props are shown, reporting it when that fails. This writes to the disk, so it
+/// is called after the state is unlocked.
+pub fn save_props(path: &Path, shown: &[String]) {
+    if let Err(error) = write_props(path, shown) {
+        log::warn!(
+            "Could not remember the shown props in '{}': {error}",
+            path.display()
+        );
+    }
+}
+
+fn write_props(path: &Path, shown: &[String]) -> Result<(), Error> {
+    fs::write(path, serde_json::to_string_pretty(shown)?)?;
+    Ok(())
+}
+
+/// Listens for the hotkeys of the props, returning a receiver that gets the name of a prop
+/// whenever its hotkey is pressed.
+pub async fn get_prop_hotkeys(props: &[PropConfig]) -> Receiver<String> {
+    let hotkeys = props
+        .iter()
+        .filter_map(|prop| {
+            let hotkey = prop.hotkey()?.parse::<Hotkey>().ok()?;
+            Some((hotkey, prop.name().to_string()))
+        })
+        .collect();
+    listen_for_hotkeys(hotkeys)
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use async_channel::Receiver;
+use rotatar_types::{ClickReactionsConfig, ReactionConfig};
+use serde::Serialize;
+use tokio::{
+    task::JoinHandle,
+    time::{Instant, timeout_at},
+};
+
+use crate::listen_for_clicks;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ClickButton {
+    Left,
+    Right,
+}
+
+impl ClickButton {
+    /// Gets the reaction configured for this button, if there is one.
+    pub fn reaction(self, config: &ClickReactionsConfig) -> Option<&ReactionConfig> {
+        match self {
+            ClickButton::Left => config.left(),
+            ClickButton::Right => config.right(),
+        }
+    }
+}
+
+/// Listens for mouse clicks, returning a receiver that gets the reaction that should be shown
+/// whenever it changes. `None` is sent once a reaction has run for its configured duration.
+///
+/// Clicking again while a reaction is shown restarts it, so the reaction stays until the clicking
+/// stops.
+pub async fn get_click_reactions(config: ClickReactionsConfig) -> Receiver<Option<ClickButton>> {
+    click_reactions(config).0
+}
+
+/// Reacts to clicks like [get_click_reactions], also returning the task that listens for them.
+/// Aborting that task unhooks it from the mouse right away.
+pub(crate) fn click_reactions(
+    config: ClickReactionsConfig,
+) -> (Receiver<Option<ClickButton>>, JoinHandle<()>) {
+    let (clicks, listener) = listen_for_clicks();
+    (react_to_clicks(config, clicks), listener)
+}
+
+/// Turns mouse clicks into the reactions that should be shown, see [get_click_reactions].
+fn react_to_clicks(
+    config: ClickReactionsConfig,
+    clicks: Receiver<ClickButton>,
+) -> Receiver<Option<ClickButton>> {
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let mut active: Option<Instant> = None;
+        loop {
+            let click = match active {
+                Some(until) => match timeout_at(until, clicks.recv()).await {
+                    Ok(click) => click,
+                    Err(_) => {
+                        active = None;
+                        if sender.send(None).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                },
+                None => clicks.recv().await,
+            };
+            let Ok(button) = click else {
+                break;
+            };
+            if let Some(reaction) = button.reaction(&config) {
+                active = Some(Instant::now() + Duration::from_millis(reaction.duration()));
+                if sender.send(Some(button)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    out_receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use async_channel::{Sender, TryRecvError};
+    use serde_json::json;
+    use tokio::time::advance;
+
+    use super::*;
+
+    /// Starts reacting to clicks with only a left reaction of 300 ms, returning where to send the
+    /// clicks and where the reactions come out.
+    fn react() -> (Sender<ClickButton>, Receiver<Option<ClickButton>>) {
+        let config = serde_json::from_value(json!({
+            "left": { "images": ["left.png"], "duration": 300 },
+        }))
+        .unwrap();
+        let (clicks, receiver) = async_channel::unbounded();
+        (clicks, react_to_clicks(config, receiver))
+    }
+
+    /// Lets the reaction task run up to where it waits again.
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_reaction_ends_after_its_duration() {
+        let (clicks, reactions) = react();
+        clicks.send(ClickButton::Left).await.unwrap();
+        assert_eq!(reactions.recv().await, Ok(Some(ClickButton::Left)));
+
+        advance(Duration::from_millis(299)).await;
+        settle().await;
+        assert_eq!(reactions.try_recv(), Err(TryRecvError::Empty));
+
+        advance(Duration::from_millis(2)).await;
+        assert_eq!(reactions.recv().await, Ok(None));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn clicking_again_restarts_the_reaction() {
+        let (clicks, reactions) = react();
+        clicks.send(ClickButton::Left).await.unwrap();
+        assert_eq!(reactions.recv().await, Ok(Some(ClickButton::Left)));
+
+        advance(Duration::from_millis(200)).await;
+        clicks.send(ClickButton::Left).await.unwrap();
+        assert_eq!(reactions.recv().await, Ok(Some(ClickButton::Left)));
+
+        // The first click would have ended the reaction by now.
+        advance(Duration::from_millis(200)).await;
+        settle().await;
+        assert_eq!(reactions.try_recv(), Err(TryRecvError::Empty));
+
+        advance(Duration::from_millis(101)).await;
+        assert_eq!(reactions.recv().await, Ok(None));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_buttons_with_a_reaction_react() {
+        let (clicks, reactions) = react();
+        clicks.send(ClickButton::Right).await.unwrap();
+        settle().await;
+        assert_eq!(reactions.try_recv(), Err(TryRecvError::Empty));
+
+        clicks.send(ClickButton::Left).await.unwrap();
+        assert_eq!(reactions.recv().await, Ok(Some(ClickButton::Left)));
+    }
+
+    #[test]
+    fn each_button_gets_its_own_reaction() {
+        let config: ClickReactionsConfig = serde_json::from_value(json!({
+            "left": { "images": ["left.png"], "duration": 100 },
+            "right": { "images": ["right.png"], "duration": 200 },
+        }))
+        .unwrap();
+        assert_eq!(ClickButton::Left.reaction(&config).unwrap().duration(), 100);
+        assert_eq!(
+            ClickButton::Right.reaction(&config).unwrap().duration(),
+            200
+        );
+        assert!(
+            ClickButton::Right
+                .reaction(&ClickReactionsConfig::default())
+                .is_none()
+        );
+    }
+}
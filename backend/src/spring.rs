@@ -0,0 +1,251 @@
+use std::time::{Duration, Instant};
+
+use async_channel::Receiver;
+use rotatar_types::SpringConfig;
+use serde::Serialize;
+
+use crate::interval;
+
+/// How often the springs are sent to the frontends.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How many seconds one step of the simulation lasts. Stepping by a fixed amount keeps the springs
+/// stable and behaving the same no matter how late a frame is.
+const STEP: f32 = 1.0 / 240.0;
+
+/// The stiffest a spring can be. Stepping stiffer springs by `STEP` would make them swing further
+/// with every step until they blow up.
+pub(crate) const MAX_STIFFNESS: f32 = 10_000.0;
+
+/// At most this many seconds are simulated at once, so that the springs don't have to catch up on
+/// a long pause all at once.
+const MAX_CATCH_UP: f32 = 0.25;
+
+/// Changes smaller than this, in pixels, are not sent, so resting springs do not cause redraws.
+const MIN_CHANGE: f32 = 0.01;
+
+/// How far each layer and prop lags behind the rest of the avatar, in pixels of the avatar's
+/// images, in the order they are in the config. Layers and props without a spring never move.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SpringOffsets {
+    layers: Vec<(f32, f32)>,
+    props: Vec<(f32, f32)>,
+}
+
+impl SpringOffsets {
+    pub fn layer(&self, index: usize) -> (f32, f32) {
+        self.layers.get(index).copied().unwrap_or_default()
+    }
+
+    pub fn prop(&self, index: usize) -> (f32, f32) {
+        self.props.get(index).copied().unwrap_or_default()
+    }
+}
+
+struct Spring {
+    config: SpringConfig,
+    position: (f32, f32),
+    velocity: (f32, f32),
+}
+
+impl Spring {
+    fn new(config: SpringConfig, anchor: (f32, f32)) -> Self {
+        Self {
+            config,
+            position: anchor,
+            velocity: (0.0, 0.0),
+        }
+    }
+
+    /// Moves the spring `STEP` seconds forward while it is pulled towards the anchor. Damping is
+    /// applied to the velocity after the step rather than before it, which can only ever slow the
+    /// spring down, so that no amount of damping makes it blow up.
+    fn step(&mut self, anchor: (f32, f32)) {
+        let axis = |position: &mut f32, velocity: &mut f32, anchor: f32| {
+            *velocity = (*velocity - self.config.stiffness() * (*position - anchor) * STEP)
+                / (1.0 + self.config.damping() * STEP);
+            *position += *velocity * STEP;
+        };
+        axis(&mut self.position.0, &mut self.velocity.0, anchor.0);
+        axis(&mut self.position.1, &mut self.velocity.1, anchor.1);
+
+        let (x, y) = self.offset(anchor);
+        let distance = x.hypot(y);
+        if distance > self.config.max_offset() {
+            let shorten = self.config.max_offset() / distance;
+            self.position = (anchor.0 + x * shorten, anchor.1 + y * shorten);
+        }
+    }
+
+    fn offset(&self, anchor: (f32, f32)) -> (f32, f32) {
+        (self.position.0 - anchor.0, self.position.1 - anchor.1)
+    }
+}
+
+/// Simulates the springs of layers and props, returning a receiver that gets their offsets
+/// whenever they change. `layers` and `props` have the spring of each layer and prop in the config,
+/// if it has one. `anchor` is called to get the section the cursor is in and how far the avatar is
+/// moved up, which is what the springs follow.
+pub async fn get_spring_offsets<F>(
+    layers: Vec<Option<SpringConfig>>,
+    props: Vec<Option<SpringConfig>>,
+    anchor: F,
+) -> Receiver<SpringOffsets>
+where
+    F: Fn() -> Option<((i32, i32), f32)> + Send + 'static,
+{
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let (section, lift) = anchor().unwrap_or_default();
+        let springs = |configs: Vec<Option<SpringConfig>>| -> Vec<Option<Spring>> {
+            configs
+                .into_iter()
+                .map(|config| Some(Spring::new(config?, config?.anchor(section, lift))))
+                .collect()
+        };
+        let (mut layers, mut props) = (springs(layers), springs(props));
+        let mut current = SpringOffsets::default();
+        let mut last_frame = Instant::now();
+        let mut unsimulated = 0.0;
+        interval!(FRAME_INTERVAL, sender, {
+            unsimulated = (unsimulated + last_frame.elapsed().as_secs_f32()).min(MAX_CATCH_UP);
+            last_frame = Instant::now();
+            let Some((section, lift)) = anchor() else {
+                continue;
+            };
+            let offsets = |springs: &mut [Option<Spring>]| -> Vec<(f32, f32)> {
+                springs
+                    .iter_mut()
+                    .map(|spring| {
+                        let Some(spring) = spring else {
+                            return (0.0, 0.0);
+                        };
+                        let anchor = spring.config.anchor(section, lift);
+                        let mut remaining = unsimulated;
+                        while remaining >= STEP {
+                            spring.step(anchor);
+                            remaining -= STEP;
+                        }
+                        spring.offset(anchor)
+                    })
+                    .collect()
+            };
+            let next = SpringOffsets {
+                layers: offsets(&mut layers),
+                props: offsets(&mut props),
+            };
+            unsimulated %= STEP;
+            if changed(&current, &next) {
+                current = next;
+                if sender.send(current.clone()).await.is_err() {
+                    break;
+                }
+            }
+        });
+    });
+    out_receiver
+}
+
+fn changed(from: &SpringOffsets, to: &SpringOffsets) -> bool {
+    let moved = |from: &Vec<(f32, f32)>, to: &Vec<(f32, f32)>| {
+        from.len() != to.len()
+            || from.iter().zip(to).any(|(from, to)| {
+                (from.0 - to.0).abs() > MIN_CHANGE || (from.1 - to.1).abs() > MIN_CHANGE
+            })
+    };
+    moved(&from.layers, &to.layers) || moved(&from.props, &to.props)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn spring(config: serde_json::Value) -> Spring {
+        Spring::new(serde_json::from_value(config).unwrap(), (0.0, 0.0))
+    }
+
+    /// Steps the spring for `seconds` while it follows the anchor.
+    fn run(spring: &mut Spring, anchor: (f32, f32), seconds: f32) {
+        for _ in 0..(seconds / STEP) as usize {
+            spring.step(anchor);
+        }
+    }
+
+    fn distance((x, y): (f32, f32)) -> f32 {
+        x.hypot(y)
+    }
+
+    #[test]
+    fn springs_settle_on_the_anchor() {
+        let mut spring = spring(json!({}));
+        run(&mut spring, (12.0, -8.0), 5.0);
+        assert!(distance(spring.offset((12.0, -8.0))) < MIN_CHANGE);
+    }
+
+    #[test]
+    fn springs_lag_behind_a_moving_anchor() {
+        let mut spring = spring(json!({}));
+        spring.step((10.0, 0.0));
+        let (x, y) = spring.offset((10.0, 0.0));
+        assert!(x < 0.0 && x > -10.0, "{x}");
+        assert_eq!(y, 0.0);
+    }
+
+    #[test]
+    fn springs_never_lag_further_than_the_max_offset() {
+        let mut spring = spring(json!({ "max_offset": 24.0 }));
+        spring.step((300.0, 400.0));
+        assert!(distance(spring.offset((300.0, 400.0))) <= 24.0 + 1e-3);
+        // The spring is held at the edge in the direction it lags behind in.
+        let (x, y) = spring.offset((300.0, 400.0));
+        assert!((x / y - 0.75).abs() < 1e-3, "{x}, {y}");
+    }
+
+    #[test]
+    fn heavily_damped_springs_creep_towards_the_anchor() {
+        for damping in [500.0, 1e6, f32::MAX] {
+            let mut spring = spring(json!({ "damping": damping }));
+            run(&mut spring, (10.0, 0.0), 5.0);
+            let (x, y) = spring.offset((10.0, 0.0));
+            assert!(x.is_finite() && y.is_finite(), "{damping}: {x}, {y}");
+            assert!((-10.0..=0.0).contains(&x), "{damping}: {x}");
+        }
+    }
+
+    #[test]
+    fn the_stiffest_springs_stay_within_the_max_offset() {
+        let mut spring = spring(json!({ "stiffness": MAX_STIFFNESS, "damping": 0.0 }));
+        run(&mut spring, (10.0, 0.0), 5.0);
+        let (x, y) = spring.offset((10.0, 0.0));
+        assert!(x.is_finite() && y.is_finite(), "{x}, {y}");
+        assert!(distance((x, y)) <= 24.0 + 1e-3, "{x}, {y}");
+    }
+
+    #[test]
+    fn undamped_springs_keep_swinging() {
+        let mut spring = spring(json!({ "damping": 0.0, "max_offset": 1000.0 }));
+        run(&mut spring, (10.0, 0.0), 5.0);
+        assert!(distance(spring.offset((10.0, 0.0))) + distance(spring.velocity) > 1.0);
+    }
+
+    #[test]
+    fn only_noticeable_changes_are_sent() {
+        let resting = SpringOffsets {
+            layers: vec![(1.0, 1.0)],
+            props: vec![],
+        };
+        let nudged = SpringOffsets {
+            layers: vec![(1.0 + MIN_CHANGE / 2.0, 1.0)],
+            props: vec![],
+        };
+        let moved = SpringOffsets {
+            layers: vec![(1.0 + MIN_CHANGE * 2.0, 1.0)],
+            props: vec![],
+        };
+        assert!(!changed(&resting, &nudged));
+        assert!(changed(&resting, &moved));
+    }
+}
@@ -0,0 +1,321 @@
+use std::{fmt, fs::read_to_string, path::Path};
+
+use rotatar_types::{ImageSource, SheetCell, SpriteSheet};
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor},
+};
+
+use crate::Error;
+
+/// The parts of a TexturePacker JSON atlas that are needed to find the cells. Both the array and
+/// the hash export formats are supported.
+#[derive(Deserialize)]
+struct Atlas {
+    frames: AtlasFrames,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AtlasFrames {
+    Array(Vec<AtlasFrame>),
+    Hash(NamedFrames),
+}
+
+/// The frames of a hash atlas, in the order they are listed in the file.
+struct NamedFrames(Vec<(String, AtlasFrame)>);
+
+#[derive(Deserialize)]
+struct AtlasFrame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: AtlasRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+}
+
+#[derive(Deserialize)]
+struct AtlasRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+/// Slices a sprite sheet into one image source for each of its cells, in the order sections are
+/// numbered in.
+pub fn slice(sheet: &SpriteSheet) -> Result<Vec<ImageSource>, Error> {
+    let path = sheet.path();
+    let (width, height) = image::image_dimensions(path).map_err(|error| {
+        Error::InvalidConfig(format!(
+            "Could not read sprite sheet '{}': {error}",
+            path.display()
+        ))
+    })?;
+    let cell_size = match (sheet.cell_size(), sheet.grid(), sheet.atlas()) {
+        (Some(cell_size), None, None) => cell_size,
+        (None, Some((columns, rows)), None) if columns > 0 && rows > 0 => {
+            (width / columns, height / rows)
+        }
+        (None, None, Some(atlas)) => return slice_atlas(sheet, atlas, (width, height)),
+        _ => {
+            return Err(Error::InvalidConfig(format!(
+                "The sprite sheet '{}' needs exactly one of a cell size, a grid with at least one column and row, or an atlas",
+                path.display()
+            )));
+        }
+    };
+    let (cell_width, cell_height) = cell_size;
+    if cell_width == 0 || cell_height == 0 || cell_width > width || cell_height > height {
+        return Err(Error::InvalidConfig(format!(
+            "The cells of sprite sheet '{}' ({cell_width}x{cell_height}) must fit into the sheet ({width}x{height})",
+            path.display()
+        )));
+    }
+    let mut cells = Vec::new();
+    for row in 0..height / cell_height {
+        for column in 0..width / cell_width {
+            cells.push(ImageSource::Cell(SheetCell::new(
+                path.clone(),
+                column * cell_width,
+                row * cell_height,
+                cell_width,
+                cell_height,
+            )));
+        }
+    }
+    Ok(cells)
+}
+
+impl<'de> Deserialize<'de> for NamedFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NamedFramesVisitor;
+
+        impl<'de> Visitor<'de> for NamedFramesVisitor {
+            type Value = NamedFrames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an object of atlas frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    frames.push(entry);
+                }
+                Ok(NamedFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_map(NamedFramesVisitor)
+    }
+}
+
+fn slice_atlas(
+    sheet: &SpriteSheet,
+    atlas_path: &Path,
+    (width, height): (u32, u32),
+) -> Result<Vec<ImageSource>, Error> {
+    let atlas: Atlas = serde_json::from_str(&read_to_string(atlas_path)?).map_err(|error| {
+        Error::InvalidConfig(format!(
+            "Could not parse sprite sheet atlas '{}': {error}",
+            atlas_path.display()
+        ))
+    })?;
+    let frames: Vec<(Option<String>, AtlasFrame)> = match atlas.frames {
+        AtlasFrames::Array(frames) => frames
+            .into_iter()
+            .map(|frame| (frame.filename.clone(), frame))
+            .collect(),
+        AtlasFrames::Hash(NamedFrames(frames)) => frames
+            .into_iter()
+            .map(|(name, frame)| (Some(name), frame))
+            .collect(),
+    };
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, AtlasFrame { frame, rotated, trimmed, .. }))| {
+            if rotated || trimmed {
+                let name = name.map_or_else(
+                    || format!("number {}", index + 1),
+                    |name| format!("'{name}'"),
+                );
+                return Err(Error::InvalidConfig(format!(
+                    "The atlas frame {name} of '{}' is {}, which is not supported. Export the atlas without rotating or trimming frames",
+                    atlas_path.display(),
+                    if rotated { "rotated" } else { "trimmed" }
+                )));
+            }
+            if frame.w == 0
+                || frame.h == 0
+                || frame.x.saturating_add(frame.w) > width
+                || frame.y.saturating_add(frame.h) > height
+            {
+                return Err(Error::InvalidConfig(format!(
+                    "The atlas frame at {}x{} ({}x{}) does not fit into sprite sheet '{}' ({width}x{height})",
+                    frame.x,
+                    frame.y,
+                    frame.w,
+                    frame.h,
+                    sheet.path().display()
+                )));
+            }
+            Ok(ImageSource::Cell(SheetCell::new(
+                sheet.path().clone(),
+                frame.x,
+                frame.y,
+                frame.w,
+                frame.h,
+            )))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use image::RgbaImage;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::directory;
+
+    /// Writes an 8x4 sheet, and an atlas if one is given, into the directory, returning the sprite
+    /// sheet config.
+    fn sheet(
+        directory: &Path,
+        options: serde_json::Value,
+        atlas: Option<serde_json::Value>,
+    ) -> SpriteSheet {
+        let path = directory.join("sheet.png");
+        RgbaImage::new(8, 4).save(&path).unwrap();
+        let mut sheet = json!({ "path": path });
+        sheet
+            .as_object_mut()
+            .unwrap()
+            .extend(options.as_object().unwrap().clone());
+        if let Some(atlas) = atlas {
+            let atlas_path = directory.join("atlas.json");
+            fs::write(&atlas_path, atlas.to_string()).unwrap();
+            sheet["atlas"] = json!(atlas_path);
+        }
+        serde_json::from_value(sheet).unwrap()
+    }
+
+    fn rects(cells: Vec<ImageSource>) -> Vec<(u32, u32, u32, u32)> {
+        cells
+            .into_iter()
+            .map(|source| match source {
+                ImageSource::Cell(cell) => (cell.x(), cell.y(), cell.width(), cell.height()),
+                other => panic!("Expected a cell, found {other:?}"),
+            })
+            .collect()
+    }
+
+    fn frame(x: u32, y: u32) -> serde_json::Value {
+        json!({ "frame": { "x": x, "y": y, "w": 4, "h": 2 } })
+    }
+
+    #[test]
+    fn grids_are_sliced_in_section_order() {
+        let directory = directory(&[]);
+        let cells = slice(&sheet(directory.path(), json!({ "grid": [2, 2] }), None)).unwrap();
+        assert_eq!(
+            rects(cells),
+            [(0, 0, 4, 2), (4, 0, 4, 2), (0, 2, 4, 2), (4, 2, 4, 2)]
+        );
+    }
+
+    #[test]
+    fn cell_sizes_leave_out_partial_cells() {
+        let directory = directory(&[]);
+        let cells = slice(&sheet(
+            directory.path(),
+            json!({ "cell_size": [3, 4] }),
+            None,
+        ))
+        .unwrap();
+        assert_eq!(rects(cells), [(0, 0, 3, 4), (3, 0, 3, 4)]);
+    }
+
+    #[test]
+    fn cells_must_fit_into_the_sheet() {
+        let directory = directory(&[]);
+        let sheet = sheet(directory.path(), json!({ "cell_size": [9, 4] }), None);
+        assert!(matches!(slice(&sheet), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn exactly_one_way_of_slicing_is_needed() {
+        let directory = directory(&[]);
+        let neither = sheet(directory.path(), json!({}), None);
+        assert!(matches!(slice(&neither), Err(Error::InvalidConfig(_))));
+        let both = sheet(
+            directory.path(),
+            json!({ "grid": [2, 2], "cell_size": [4, 2] }),
+            None,
+        );
+        assert!(matches!(slice(&both), Err(Error::InvalidConfig(_))));
+        let empty_grid = sheet(directory.path(), json!({ "grid": [0, 2] }), None);
+        assert!(matches!(slice(&empty_grid), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn array_atlases_keep_their_order() {
+        let directory = directory(&[]);
+        let atlas = json!({ "frames": [frame(4, 2), frame(0, 0)] });
+        let cells = slice(&sheet(directory.path(), json!({}), Some(atlas))).unwrap();
+        assert_eq!(rects(cells), [(4, 2, 4, 2), (0, 0, 4, 2)]);
+    }
+
+    #[test]
+    fn hash_atlases_keep_the_order_of_the_file() {
+        let directory = directory(&[]);
+        // Sorting by name would put `frame10` before `frame2`.
+        let atlas = r#"{ "frames": {
+            "frame2": { "frame": { "x": 0, "y": 0, "w": 4, "h": 2 } },
+            "frame10": { "frame": { "x": 4, "y": 0, "w": 4, "h": 2 } },
+            "frame1": { "frame": { "x": 0, "y": 2, "w": 4, "h": 2 } }
+        } }"#;
+        let sheet = sheet(
+            directory.path(),
+            json!({}),
+            Some(serde_json::from_str(atlas).unwrap()),
+        );
+        // `serde_json::Value` sorts objects by key, so the atlas is written as it is.
+        fs::write(sheet.atlas().unwrap(), atlas).unwrap();
+        assert_eq!(
+            rects(slice(&sheet).unwrap()),
+            [(0, 0, 4, 2), (4, 0, 4, 2), (0, 2, 4, 2)]
+        );
+    }
+
+    #[test]
+    fn rotated_and_trimmed_frames_are_rejected() {
+        let directory = directory(&[]);
+        for flag in ["rotated", "trimmed"] {
+            let mut rotated = frame(0, 0);
+            rotated[flag] = json!(true);
+            let atlas = json!({ "frames": { "head": rotated } });
+            let Err(Error::InvalidConfig(message)) =
+                slice(&sheet(directory.path(), json!({}), Some(atlas)))
+            else {
+                panic!("A {flag} frame should be rejected");
+            };
+            assert!(message.contains("'head' of"), "{message}");
+            assert!(message.contains(flag), "{message}");
+        }
+    }
+
+    #[test]
+    fn atlas_frames_must_fit_into_the_sheet() {
+        let directory = directory(&[]);
+        let atlas = json!({ "frames": [frame(6, 0)] });
+        let sheet = sheet(directory.path(), json!({}), Some(atlas));
+        assert!(matches!(slice(&sheet), Err(Error::InvalidConfig(_))));
+    }
+}
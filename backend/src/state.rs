@@ -14,19 +14,26 @@ macro_rules! set_state {
     };
 }
 
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
 use async_channel::{Receiver, Sender};
-use rotatar_types::TwoInts;
+use rotatar_types::{Config, ImageSource, MotionDriver, Transform, TwoInts};
 use serde::Serialize;
 
 use crate::{
-    Message,
+    AfkTimer, AnimationFrames, ClickButton, Message, SpringOffsets, Transition, TypingActivity,
     audio::{AudioMessage, AudioStatus},
+    prop,
 };
 
 #[derive(Clone, Serialize)]
 pub struct State {
     current_image: usize,
     sensitivity: f32,
+    magnitude: i32,
     #[serde(skip_serializing)]
     message_sender: Sender<Message>,
 
@@ -37,8 +44,54 @@ pub struct State {
     audio_receiver: Receiver<AudioMessage>,
     audio_devices: Vec<String>,
 
+    screen_size: TwoInts,
     section_size: (i32, i32),
     x_sections: i32,
+    y_sections: i32,
+    cursor_position: TwoInts,
+    /// The cursor position in sections, where the whole part is the section and the fraction is
+    /// how far into that section the cursor is.
+    section_position: (f32, f32),
+    cursor_outside: bool,
+
+    typing: TypingActivity,
+
+    #[serde(skip_serializing)]
+    afk_timer: AfkTimer,
+    /// The current frame of the sleeping animation, or `None` while awake.
+    sleeping: Option<usize>,
+
+    /// The click reaction currently overriding the section image.
+    reaction: Option<ClickButton>,
+
+    /// The index of the idle action currently overriding the section image.
+    idle_action: Option<usize>,
+
+    /// The frame of every shown image that is animated.
+    #[serde(skip_serializing)]
+    frames: AnimationFrames,
+
+    blinking: bool,
+
+    /// How the avatar is currently moved, scaled and rotated.
+    transform: Transform,
+
+    /// The transition away from the previously shown image, while one is playing.
+    transition: Option<Transition>,
+
+    /// The name of the active expression, or `None` for the default images.
+    expression: Option<String>,
+    #[serde(skip_serializing)]
+    expression_since: Instant,
+
+    /// The names of the props that are shown.
+    props: Vec<String>,
+    /// The file the shown props are remembered in, once they have been restored from it.
+    #[serde(skip_serializing)]
+    props_path: Option<PathBuf>,
+
+    /// How far springy layers and props lag behind the rest of the avatar.
+    spring_offsets: SpringOffsets,
 }
 
 impl State {
@@ -49,31 +102,95 @@ impl State {
         audio_sender: Sender<AudioMessage>,
         audio_receiver: Receiver<AudioMessage>,
     ) -> Self {
+        // Sections are validated when the config is loaded, this only keeps the divisions below
+        // from ever dividing by zero.
+        let sections = (sections.0.max(1), sections.1.max(1));
         let mut state = Self {
             current_image: 0,
             sensitivity: 0.0,
+            magnitude: 0,
             message_sender,
             audio_status: AudioStatus::Closed,
             audio_sender,
             audio_receiver,
             audio_devices: Vec::new(),
-            section_size: (screen_size.x() / sections.0, screen_size.y() / sections.1),
+            screen_size,
+            section_size: (
+                (screen_size.x() / sections.0).max(1),
+                (screen_size.y() / sections.1).max(1),
+            ),
             x_sections: sections.0,
+            y_sections: sections.1,
+            cursor_position: TwoInts::default(),
+            section_position: (0.0, 0.0),
+            cursor_outside: false,
+            typing: TypingActivity::default(),
+            afk_timer: AfkTimer::new(),
+            sleeping: None,
+            reaction: None,
+            idle_action: None,
+            frames: AnimationFrames::default(),
+            blinking: false,
+            transform: Transform::default(),
+            transition: None,
+            expression: None,
+            expression_since: Instant::now(),
+            props: Vec::new(),
+            props_path: None,
+            spring_offsets: SpringOffsets::default(),
         };
         state.set_current_image_xy(state.section_size().0 / 2, state.section_size().1 / 2);
         state
     }
 
+    /// Fits the state to a config that was reloaded. The sections are laid out again, and the
+    /// expression and props are dropped if the new config no longer has them. Everything the loops
+    /// set is reset, as the loops start over with the new config.
+    pub fn reload(&mut self, config: &Config) {
+        let screen_size = config.screen_information().size();
+        let sections = config.sections();
+        let sections = (sections.0.max(1), sections.1.max(1));
+        self.screen_size = screen_size;
+        self.section_size = (
+            (screen_size.x() / sections.0).max(1),
+            (screen_size.y() / sections.1).max(1),
+        );
+        self.x_sections = sections.0;
+        self.y_sections = sections.1;
+        // The current image is worked out from scratch, as its index may not be a section anymore.
+        self.current_image = usize::MAX;
+        self.set_current_image_xy(self.cursor_position.x(), self.cursor_position.y());
+
+        if self
+            .expression
+            .as_ref()
+            .is_some_and(|name| config.expression(name).is_none())
+        {
+            self.set_expression(None);
+        }
+        self.props.retain(|name| config.prop(name).is_some());
+        self.typing = TypingActivity::default();
+        self.sleeping = None;
+        self.reaction = None;
+        self.idle_action = None;
+        self.frames = AnimationFrames::default();
+        self.blinking = false;
+        self.transform = Transform::default();
+        self.transition = None;
+        self.spring_offsets = SpringOffsets::default();
+    }
+
     pub fn current_image(&self) -> usize {
         self.current_image
     }
 
     /// Attempts to set the current image, returning true if successful. Returns false when the new
-    /// image is the same as the old, nothing will be set.
+    /// image is the same as the old, or when the index is not a valid section. In both cases
+    /// nothing will be set.
     pub fn set_current_image(&mut self, current_image: usize) -> bool {
         if self.current_image == current_image {
             false
-        } else if current_image <= (self.section_size.0 * self.section_size.1) as usize {
+        } else if current_image < self.total_sections() {
             self.current_image = current_image;
             true
         } else {
@@ -81,13 +198,230 @@ impl State {
         }
     }
 
+    /// Sets the current image from a screen position. Positions outside of the screen are clamped
+    /// into the closest section and mark the cursor as outside.
+    ///
+    /// Returns true if either the current image or whether the cursor is outside changed.
     pub fn set_current_image_xy(&mut self, x: i32, y: i32) -> bool {
-        let new_image = to_2d_index(
-            x / self.section_size.0,
-            y / self.section_size.1,
-            self.x_sections,
+        if (self.cursor_position.x(), self.cursor_position.y()) != (x, y) {
+            self.cursor_position = TwoInts::new(x, y);
+            self.afk_timer.wake();
+        }
+        let cursor_outside =
+            x < 0 || y < 0 || x >= self.screen_size.x() || y >= self.screen_size.y();
+        let outside_changed = self.cursor_outside != cursor_outside;
+        self.cursor_outside = cursor_outside;
+
+        let clamped_x = x.clamp(0, (self.screen_size.x() - 1).max(0));
+        let clamped_y = y.clamp(0, (self.screen_size.y() - 1).max(0));
+        self.section_position = (
+            (clamped_x as f32 / self.section_size.0 as f32).min(self.x_sections as f32),
+            (clamped_y as f32 / self.section_size.1 as f32).min(self.y_sections as f32),
         );
-        self.set_current_image(new_image)
+
+        let (section_x, section_y) = self.section_xy(x, y);
+        let new_image = to_2d_index(section_x, section_y, self.x_sections);
+        self.set_current_image(new_image) || outside_changed
+    }
+
+    /// Gets the section that contains the position, clamping positions outside of the grid.
+    pub fn section_xy(&self, x: i32, y: i32) -> (i32, i32) {
+        let x = x.clamp(0, (self.screen_size.x() - 1).max(0));
+        let y = y.clamp(0, (self.screen_size.y() - 1).max(0));
+        (
+            (x / self.section_size.0).min(self.x_sections - 1),
+            (y / self.section_size.1).min(self.y_sections - 1),
+        )
+    }
+
+    /// Gets the image that should currently be shown, based on the current section,
+    /// whether the avatar is asleep, any click reaction, any idle action, whether the cursor is
+    /// outside, whether the avatar is blinking, whether the avatar is speaking and whether the user
+    /// is typing.
+    pub fn current_image_source<'a>(&self, config: &'a Config) -> Option<&'a ImageSource> {
+        self.override_image_source(config)
+            .or_else(|| self.section_image_source(config, self.current_image))
+    }
+
+    /// Gets the images to blend together and how much each of them is weighted, which adds up to
+    /// one. When blending sections is enabled, these are the four sections closest to the cursor.
+    /// Otherwise, this is only the current image.
+    pub fn current_blend<'a>(&self, config: &'a Config) -> Vec<(&'a ImageSource, f32)> {
+        if !config.transition().blend_sections() || self.override_image_source(config).is_some() {
+            return self
+                .current_image_source(config)
+                .map(|source| vec![(source, 1.0)])
+                .unwrap_or_default();
+        }
+        // Section centers sit at half way through each section, so blending is done relative to
+        // them.
+        let x = (self.section_position.0 - 0.5).clamp(0.0, (self.x_sections - 1) as f32);
+        let y = (self.section_position.1 - 0.5).clamp(0.0, (self.y_sections - 1) as f32);
+        let (left, top) = (x.floor() as i32, y.floor() as i32);
+        let right = (left + 1).min(self.x_sections - 1);
+        let bottom = (top + 1).min(self.y_sections - 1);
+        let (x_amount, y_amount) = (x - left as f32, y - top as f32);
+        let mut blend: Vec<(&ImageSource, f32)> = Vec::new();
+        for (section_x, section_y, weight) in [
+            (left, top, (1.0 - x_amount) * (1.0 - y_amount)),
+            (right, top, x_amount * (1.0 - y_amount)),
+            (left, bottom, (1.0 - x_amount) * y_amount),
+            (right, bottom, x_amount * y_amount),
+        ] {
+            let section = to_2d_index(section_x, section_y, self.x_sections);
+            let Some(source) = self.section_image_source(config, section) else {
+                continue;
+            };
+            match blend.iter_mut().find(|(blended, _)| *blended == source) {
+                Some((_, blended_weight)) => *blended_weight += weight,
+                None => blend.push((source, weight)),
+            }
+        }
+        blend.retain(|(_, weight)| *weight > 0.0);
+        blend
+    }
+
+    /// Gets the image for a section, ignoring images that replace the whole avatar.
+    fn section_image_source<'a>(
+        &self,
+        config: &'a Config,
+        section: usize,
+    ) -> Option<&'a ImageSource> {
+        let expression_image = self
+            .expression
+            .as_ref()
+            .and_then(|name| config.expression(name))
+            .and_then(|expression| {
+                if self.is_speaking() {
+                    expression.speaking_images().get(section)
+                } else {
+                    expression.idle_images().get(section)
+                }
+            });
+        if expression_image.is_some() {
+            return expression_image;
+        }
+        // Typing images win over blinking images, unless they have eyes closed variants of their
+        // own, so that blinking doesn't swap the typing images out.
+        let typing_image = config
+            .typing()
+            .filter(|_| self.typing.is_active())
+            .and_then(|typing| {
+                let blinking_image = if self.is_speaking() {
+                    typing.blinking_speaking_images().get(section)
+                } else {
+                    typing.blinking_idle_images().get(section)
+                };
+                blinking_image.filter(|_| self.blinking).or_else(|| {
+                    if self.is_speaking() {
+                        typing.speaking_images().get(section)
+                    } else {
+                        typing.idle_images().get(section)
+                    }
+                })
+            });
+        let blink_image = config.blink().filter(|_| self.blinking).and_then(|blink| {
+            if self.is_speaking() {
+                blink.speaking_images().get(section)
+            } else {
+                blink.idle_images().get(section)
+            }
+        });
+        if typing_image.is_some() {
+            typing_image
+        } else if blink_image.is_some() {
+            blink_image
+        } else if self.is_speaking() {
+            config.speaking_images().get(section)
+        } else {
+            config.idle_images().get(section)
+        }
+    }
+
+    /// Gets the image of every layer that should currently be shown, from bottom to top, with how
+    /// far its spring moves it. Layers are hidden while the avatar is asleep, reacting to a click
+    /// or while the cursor is outside, as those images replace the whole avatar.
+    pub fn current_layer_sources<'a>(
+        &self,
+        config: &'a Config,
+    ) -> Vec<(&'a ImageSource, (f32, f32))> {
+        if self.override_image_source(config).is_some() {
+            return Vec::new();
+        }
+        let expression = self
+            .expression
+            .as_ref()
+            .and_then(|name| config.expression(name));
+        config
+            .layers()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, layer)| {
+                let source = expression
+                    .and_then(|expression| expression.layer_image(layer.name(), self.current_image))
+                    .or_else(|| {
+                        layer.image(self.current_image, self.is_speaking(), self.blinking)
+                    })?;
+                Some((source, self.spring_offsets.layer(index)))
+            })
+            .collect()
+    }
+
+    /// Gets the image and offset of every prop that is shown, from bottom to top, including how far
+    /// its spring moves it. Props are hidden
+    /// whenever layers are, as the images that replace the whole avatar may not line up with them.
+    pub fn current_prop_sources<'a>(
+        &self,
+        config: &'a Config,
+    ) -> Vec<(&'a ImageSource, (f32, f32))> {
+        if self.override_image_source(config).is_some() {
+            return Vec::new();
+        }
+        config
+            .props()
+            .iter()
+            .enumerate()
+            .filter(|(_, prop)| self.props.iter().any(|name| name == prop.name()))
+            .map(|(index, prop)| {
+                let (x, y) = prop.offset(self.current_image);
+                let (spring_x, spring_y) = self.spring_offsets.prop(index);
+                (prop.image(), (x + spring_x, y + spring_y))
+            })
+            .collect()
+    }
+
+    /// Gets the image that replaces the whole avatar, if any.
+    fn override_image_source<'a>(&self, config: &'a Config) -> Option<&'a ImageSource> {
+        let outside_images = config.outside_images().filter(|_| self.cursor_outside);
+        let sleeping_image = config.afk().and_then(|afk| {
+            self.sleeping
+                .and_then(|frame| afk.sleeping_images().get(frame))
+        });
+        let reaction_image = config.click_reactions().and_then(|click_reactions| {
+            self.reaction
+                .and_then(|button| button.reaction(click_reactions))
+                .and_then(|reaction| reaction.image(self.current_image))
+        });
+        let idle_action_image = config.idle_actions().and_then(|idle_actions| {
+            self.idle_action
+                .and_then(|index| idle_actions.actions().get(index))
+                .and_then(|action| action.image(self.current_image))
+        });
+        if sleeping_image.is_some() {
+            sleeping_image
+        } else if reaction_image.is_some() {
+            reaction_image
+        } else if idle_action_image.is_some() {
+            idle_action_image
+        } else {
+            outside_images.map(|outside_images| {
+                if self.is_speaking() {
+                    outside_images.speaking()
+                } else {
+                    outside_images.idle()
+                }
+            })
+        }
     }
 
     pub fn sensitivity(&self) -> f32 {
@@ -96,6 +430,116 @@ impl State {
 
     pub fn set_sensitivity(&mut self, sensitivity: f32) {
         self.sensitivity = sensitivity;
+        if self.is_speaking() {
+            self.afk_timer.wake();
+        }
+    }
+
+    pub fn magnitude(&self) -> i32 {
+        self.magnitude
+    }
+
+    pub fn set_magnitude(&mut self, magnitude: i32) {
+        self.magnitude = magnitude;
+    }
+
+    /// Gets the volume that drives the motion, between 0 and 1.
+    pub fn motion_level(&self, config: &Config) -> f32 {
+        match config.motion().map(|motion| motion.driver()) {
+            Some(MotionDriver::Magnitude) => {
+                self.magnitude as f32 / config.audio().max_magnitude().max(1) as f32
+            }
+            Some(MotionDriver::Sensitivity) | None => self.sensitivity,
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn spring_offsets(&self) -> &SpringOffsets {
+        &self.spring_offsets
+    }
+
+    pub fn set_spring_offsets(&mut self, spring_offsets: SpringOffsets) {
+        self.spring_offsets = spring_offsets;
+    }
+
+    /// Gets what the springs of layers and props follow, which is the section the cursor is in
+    /// and how far the avatar is moved up.
+    pub fn spring_anchor(&self) -> ((i32, i32), f32) {
+        let section = self.current_image as i32;
+        (
+            (section % self.x_sections, section / self.x_sections),
+            self.transform.offset(),
+        )
+    }
+
+    pub fn transition(&self) -> Option<&Transition> {
+        self.transition.as_ref()
+    }
+
+    pub fn set_transition(&mut self, transition: Option<Transition>) {
+        self.transition = transition;
+    }
+
+    pub fn expression(&self) -> Option<&String> {
+        self.expression.as_ref()
+    }
+
+    /// Sets the active expression, returning true if it changed.
+    pub fn set_expression(&mut self, expression: Option<String>) -> bool {
+        self.expression_since = Instant::now();
+        let changed = self.expression != expression;
+        self.expression = expression;
+        changed
+    }
+
+    /// Whether the active expression has been active for longer than its timeout.
+    pub fn is_expression_expired(&self, config: &Config) -> bool {
+        self.expression
+            .as_ref()
+            .and_then(|name| config.expression(name)?.timeout())
+            .is_some_and(|timeout| {
+                self.expression_since.elapsed() >= Duration::from_millis(timeout)
+            })
+    }
+
+    pub fn props(&self) -> &Vec<String> {
+        &self.props
+    }
+
+    /// Shows the props that were shown last time, remembering them in `path` from now on.
+    pub fn restore_props(&mut self, path: PathBuf, config: &Config) {
+        self.props = prop::load_props(&path, config.props());
+        self.props_path = Some(path);
+    }
+
+    /// Shows or hides the named prop, returning true if that changed. The caller remembers the
+    /// shown props whenever they change, with [`Self::saved_props`].
+    pub fn set_prop(&mut self, name: String, shown: bool) -> bool {
+        let was_shown = self.props.contains(&name);
+        if was_shown == shown {
+            return false;
+        }
+        if shown {
+            self.props.push(name);
+        } else {
+            self.props.retain(|prop| *prop != name);
+        }
+        true
+    }
+
+    /// The file the shown props are remembered in, together with the props to write to it. The
+    /// file is written with [`crate::save_props`] once the state is unlocked, so that the loops
+    /// changing the state don't wait on the disk.
+    pub fn saved_props(&self) -> Option<(PathBuf, Vec<String>)> {
+        let path = self.props_path.clone()?;
+        Some((path, self.props.clone()))
     }
 
     pub fn message_sender(&self) -> Sender<Message> {
@@ -130,6 +574,112 @@ impl State {
         self.x_sections
     }
 
+    pub fn y_sections(&self) -> i32 {
+        self.y_sections
+    }
+
+    pub fn total_sections(&self) -> usize {
+        (self.x_sections * self.y_sections) as usize
+    }
+
+    pub fn section_position(&self) -> (f32, f32) {
+        self.section_position
+    }
+
+    pub fn is_cursor_outside(&self) -> bool {
+        self.cursor_outside
+    }
+
+    pub fn typing(&self) -> &TypingActivity {
+        &self.typing
+    }
+
+    pub fn set_typing(&mut self, typing: TypingActivity) {
+        self.typing = typing;
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.typing.is_active()
+    }
+
+    pub fn cursor_position(&self) -> TwoInts {
+        self.cursor_position
+    }
+
+    pub fn afk_timer(&self) -> AfkTimer {
+        self.afk_timer.clone()
+    }
+
+    pub fn sleeping(&self) -> Option<usize> {
+        self.sleeping
+    }
+
+    pub fn set_sleeping(&mut self, sleeping: Option<usize>) {
+        self.sleeping = sleeping;
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping.is_some()
+    }
+
+    pub fn reaction(&self) -> Option<ClickButton> {
+        self.reaction
+    }
+
+    pub fn set_reaction(&mut self, reaction: Option<ClickButton>) {
+        if reaction.is_some() {
+            self.afk_timer.wake();
+        }
+        self.reaction = reaction;
+    }
+
+    pub fn idle_action(&self) -> Option<usize> {
+        self.idle_action
+    }
+
+    pub fn set_idle_action(&mut self, idle_action: Option<usize>) {
+        self.idle_action = idle_action;
+    }
+
+    /// Whether the user has not spoken and the cursor has not moved for at least `quiet_time`
+    /// milliseconds, which is when idle actions may play.
+    pub fn is_quiet(&self, quiet_time: u64) -> bool {
+        !self.is_speaking() && self.afk_timer.idle_time() >= Duration::from_millis(quiet_time)
+    }
+
+    /// Gets the frame a shown image is at.
+    pub fn frame(&self, source: &ImageSource) -> usize {
+        self.frames.get(source)
+    }
+
+    pub fn set_frames(&mut self, frames: AnimationFrames) {
+        self.frames = frames;
+    }
+
+    /// Gets every image that is currently shown, which are the blended section images, layers and
+    /// props. This is what animations are played for.
+    pub fn shown_image_sources(&self, config: &Config) -> Vec<ImageSource> {
+        self.current_blend(config)
+            .into_iter()
+            .map(|(source, _)| source)
+            .chain(
+                self.current_layer_sources(config)
+                    .into_iter()
+                    .chain(self.current_prop_sources(config))
+                    .map(|(source, _)| source),
+            )
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_blinking(&self) -> bool {
+        self.blinking
+    }
+
+    pub fn set_blinking(&mut self, blinking: bool) {
+        self.blinking = blinking;
+    }
+
     pub fn is_speaking(&self) -> bool {
         self.sensitivity > 0.0
     }
@@ -138,3 +688,187 @@ impl State {
 fn to_2d_index(x: i32, y: i32, width: i32) -> usize {
     (y * width + x) as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util;
+
+    fn config(sections: (i32, i32), mut extra: serde_json::Value) -> Config {
+        extra["sections"] = json!([sections.0, sections.1]);
+        test_util::config(extra)
+    }
+
+    fn state(sections: (i32, i32)) -> State {
+        let (message_sender, _) = async_channel::unbounded();
+        let (audio_sender, audio_receiver) = async_channel::unbounded();
+        State::new(
+            message_sender,
+            TwoInts::new(100, 100),
+            sections,
+            audio_sender,
+            audio_receiver,
+        )
+    }
+
+    #[test]
+    fn reloading_lays_the_sections_out_again() {
+        let mut state = state((2, 2));
+        state.set_current_image_xy(80, 10);
+        assert_eq!(state.current_image(), 1);
+
+        state.reload(&config((4, 1), json!({})));
+        assert_eq!(state.section_size(), (25, 100));
+        assert_eq!(state.section_xy(80, 10), (3, 0));
+        assert_eq!(state.current_image(), 3);
+    }
+
+    #[test]
+    fn reloading_keeps_the_cursor_on_a_section_that_still_exists() {
+        let mut state = state((4, 4));
+        state.set_current_image_xy(90, 90);
+        assert_eq!(state.current_image(), 15);
+
+        state.reload(&config((1, 1), json!({})));
+        assert_eq!(state.current_image(), 0);
+        assert!(!state.set_current_image(1));
+    }
+
+    #[test]
+    fn reloading_drops_the_expression_and_props_the_config_no_longer_has() {
+        let mut state = state((1, 1));
+        state.set_expression(Some(String::from("happy")));
+        state.set_prop(String::from("hat"), true);
+        state.set_prop(String::from("scarf"), true);
+
+        state.reload(&config(
+            (1, 1),
+            json!({ "props": [{ "name": "scarf", "image": "scarf.png" }] }),
+        ));
+        assert_eq!(state.expression(), None);
+        assert_eq!(state.props(), &vec![String::from("scarf")]);
+
+        state.set_expression(Some(String::from("happy")));
+        state.reload(&config((1, 1), json!({ "expressions": { "happy": {} } })));
+        assert_eq!(state.expression(), Some(&String::from("happy")));
+    }
+
+    #[test]
+    fn reloading_resets_what_the_loops_set() {
+        let mut state = state((1, 1));
+        state.set_sleeping(Some(2));
+        state.set_reaction(Some(ClickButton::Left));
+        state.set_idle_action(Some(0));
+        state.set_blinking(true);
+
+        state.reload(&config((1, 1), json!({})));
+        assert_eq!(state.sleeping(), None);
+        assert_eq!(state.reaction(), None);
+        assert_eq!(state.idle_action(), None);
+        assert!(!state.is_blinking());
+    }
+
+    /// A 2x2 grid with an image for each section, which are blended when `blend` is set.
+    fn blend_config(blend: bool) -> Config {
+        config(
+            (2, 2),
+            json!({
+                "idle_images": ["0.png", "1.png", "2.png", "3.png"],
+                "transition": { "blend_sections": blend },
+            }),
+        )
+    }
+
+    fn blend_at(state: &mut State, config: &Config, x: i32, y: i32) -> Vec<(String, f32)> {
+        state.set_current_image_xy(x, y);
+        state
+            .current_blend(config)
+            .into_iter()
+            .map(|(source, weight)| (source.paths()[0].display().to_string(), weight))
+            .collect()
+    }
+
+    #[test]
+    fn blend_weights_add_up_to_one_everywhere() {
+        let config = blend_config(true);
+        let mut state = state((2, 2));
+        for x in (-10..=110).step_by(5) {
+            for y in (-10..=110).step_by(5) {
+                let blend = blend_at(&mut state, &config, x, y);
+                let total: f32 = blend.iter().map(|(_, weight)| weight).sum();
+                assert!((total - 1.0).abs() < 1e-5, "{total} at ({x}, {y})");
+                assert!(blend.iter().all(|(_, weight)| *weight > 0.0), "({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn blending_only_shows_one_section_at_its_center_and_in_the_corners() {
+        let config = blend_config(true);
+        let mut state = state((2, 2));
+        assert_eq!(
+            blend_at(&mut state, &config, 25, 25),
+            [("0.png".into(), 1.0)]
+        );
+        assert_eq!(blend_at(&mut state, &config, 0, 0), [("0.png".into(), 1.0)]);
+        assert_eq!(
+            blend_at(&mut state, &config, 99, 0),
+            [("1.png".into(), 1.0)]
+        );
+        assert_eq!(
+            blend_at(&mut state, &config, 0, 99),
+            [("2.png".into(), 1.0)]
+        );
+        assert_eq!(
+            blend_at(&mut state, &config, 99, 99),
+            [("3.png".into(), 1.0)]
+        );
+    }
+
+    #[test]
+    fn blending_splits_the_weight_between_neighbouring_sections() {
+        let config = blend_config(true);
+        let mut state = state((2, 2));
+        assert_eq!(
+            blend_at(&mut state, &config, 0, 50),
+            [("0.png".into(), 0.5), ("2.png".into(), 0.5)]
+        );
+        assert_eq!(
+            blend_at(&mut state, &config, 50, 50),
+            [
+                ("0.png".into(), 0.25),
+                ("1.png".into(), 0.25),
+                ("2.png".into(), 0.25),
+                ("3.png".into(), 0.25),
+            ]
+        );
+    }
+
+    #[test]
+    fn sections_with_the_same_image_are_blended_once() {
+        let config = config(
+            (2, 2),
+            json!({
+                "idle_images": ["0.png", "0.png", "2.png", "2.png"],
+                "transition": { "blend_sections": true },
+            }),
+        );
+        let mut state = state((2, 2));
+        assert_eq!(
+            blend_at(&mut state, &config, 50, 50),
+            [("0.png".into(), 0.5), ("2.png".into(), 0.5)]
+        );
+    }
+
+    #[test]
+    fn only_the_current_image_is_shown_without_blending() {
+        let config = blend_config(false);
+        let mut state = state((2, 2));
+        assert_eq!(
+            blend_at(&mut state, &config, 50, 50),
+            [("3.png".into(), 1.0)]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use image::{ImageFormat, RgbaImage};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{Options, Tree, fontdb::Database},
+};
+
+/// The largest width or height, in pixels, that SVG images are rasterized at. This is larger than
+/// any display, and keeps a single raster under 256 MiB.
+pub const MAX_SIZE: u32 = 8192;
+
+/// Checks if the path is an SVG image, which has to be rasterized before it can be drawn.
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"))
+}
+
+/// Gets the size the SVG image is meant to be shown at, rounded up to whole pixels.
+pub fn size(path: &Path) -> Result<(u32, u32), String> {
+    let size = parse(path)?.size();
+    Ok((size.width().ceil() as u32, size.height().ceil() as u32))
+}
+
+/// Renders the SVG image at the path, stretched to exactly `width` by `height` pixels. Frontends
+/// are expected to keep the result around until the size they show the image at changes. Sizes
+/// larger than [`MAX_SIZE`] are refused.
+pub fn rasterize(path: &Path, (width, height): (u32, u32)) -> Result<RgbaImage, String> {
+    if width > MAX_SIZE || height > MAX_SIZE {
+        return Err(format!(
+            "Could not rasterize '{}' at {width}x{height}, which is larger than {MAX_SIZE} pixels",
+            path.display()
+        ));
+    }
+    let tree = parse(path)?;
+    let mut pixmap = Pixmap::new(width, height).ok_or_else(|| {
+        format!(
+            "Could not rasterize '{}' at {width}x{height}",
+            path.display()
+        )
+    })?;
+    let size = tree.size();
+    resvg::render(
+        &tree,
+        Transform::from_scale(width as f32 / size.width(), height as f32 / size.height()),
+        &mut pixmap.as_mut(),
+    );
+    // The pixmap is premultiplied, which nothing else expects.
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| format!("Could not rasterize '{}'", path.display()))
+}
+
+/// Rasterizes the SVG image like [`rasterize`], encoding it as a PNG for frontends that can't show
+/// raw pixels.
+pub fn rasterize_png(path: &Path, size: (u32, u32)) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    rasterize(path, size)?
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|error| format!("Could not encode '{}': {error}", path.display()))?;
+    Ok(png)
+}
+
+fn parse(path: &Path) -> Result<Tree, String> {
+    // Loading the system fonts is slow, so it's only done once for every SVG image.
+    static FONTS: OnceLock<Arc<Database>> = OnceLock::new();
+    let fontdb = FONTS
+        .get_or_init(|| {
+            let mut fontdb = Database::new();
+            fontdb.load_system_fonts();
+            Arc::new(fontdb)
+        })
+        .clone();
+    let options = Options {
+        resources_dir: path.parent().map(Path::to_path_buf),
+        fontdb,
+        ..Options::default()
+    };
+    let data =
+        fs::read(path).map_err(|error| format!("Could not read '{}': {error}", path.display()))?;
+    Tree::from_data(&data, &options)
+        .map_err(|error| format!("Could not parse SVG image '{}': {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_over_the_limit_are_refused_before_anything_is_allocated() {
+        let error = rasterize(Path::new("missing.svg"), (MAX_SIZE + 1, 1)).unwrap_err();
+        assert!(error.contains("larger than"), "{error}");
+    }
+}
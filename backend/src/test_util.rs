@@ -0,0 +1,42 @@
+//! Fixtures shared by the tests of the backend.
+
+use std::fs;
+
+use rotatar_types::Config;
+use serde_json::json;
+use tempfile::TempDir;
+
+use crate::Error;
+
+/// Builds a config with a single section from the JSON of the rest of it, which may also replace
+/// the sections and screen information.
+pub fn config(rest: serde_json::Value) -> Config {
+    let mut config = json!({
+        "sections": [1, 1],
+        "screen_information": { "size": [100, 100], "modifiers": {} },
+    });
+    if let (Some(config), Some(rest)) = (config.as_object_mut(), rest.as_object()) {
+        config.extend(rest.clone());
+    }
+    serde_json::from_value(config).unwrap()
+}
+
+/// Creates a temporary directory with an empty file for each of the names, which is removed again
+/// once it is dropped.
+pub fn directory(files: &[&str]) -> TempDir {
+    let directory = tempfile::tempdir().unwrap();
+    for file in files {
+        let path = directory.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, []).unwrap();
+    }
+    directory
+}
+
+/// Gets the message of an invalid config error, panicking on anything else.
+pub fn invalid_config_message<T: std::fmt::Debug>(result: Result<T, Error>) -> String {
+    match result {
+        Err(Error::InvalidConfig(message)) => message,
+        other => panic!("Expected an invalid config, found {other:?}"),
+    }
+}
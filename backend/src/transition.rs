@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use async_channel::Receiver;
+use rotatar_types::{ImageSource, TransitionConfig};
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::interval;
+
+/// How often a playing transition is updated, which is also how quickly a new image is noticed.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// A transition from the previously shown image to the current one.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Transition {
+    previous: ImageSource,
+    /// How far along the transition is, from 0 to 1.
+    progress: f32,
+}
+
+impl Transition {
+    pub fn previous(&self) -> &ImageSource {
+        &self.previous
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+}
+
+/// Plays a transition whenever the shown image changes, returning a receiver that gets the
+/// transition every frame while it plays, and `None` once it is done. `current_image` is called to
+/// find out which image is currently shown.
+///
+/// When the image changes again during a transition, a new transition starts from the image that
+/// was being transitioned to.
+pub async fn get_transitions<F>(
+    config: TransitionConfig,
+    current_image: F,
+) -> Receiver<Option<Transition>>
+where
+    F: Fn() -> Option<ImageSource> + Send + 'static,
+{
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    tokio::spawn(async move {
+        let duration = Duration::from_millis(config.duration().max(1));
+        let mut last_image = current_image();
+        let mut playing: Option<(ImageSource, Instant)> = None;
+        interval!(FRAME_INTERVAL, sender, {
+            let image = current_image();
+            if image != last_image {
+                playing = last_image.map(|previous| (previous, Instant::now()));
+                last_image = image;
+            }
+            let Some((previous, started)) = &playing else {
+                continue;
+            };
+            let progress = started.elapsed().as_secs_f32() / duration.as_secs_f32();
+            let transition = (progress < 1.0).then(|| Transition {
+                previous: previous.clone(),
+                progress,
+            });
+            if transition.is_none() {
+                playing = None;
+            }
+            if sender.send(transition).await.is_err() {
+                break;
+            }
+        });
+    });
+    out_receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+    use tokio::time::sleep;
+
+    use super::*;
+
+    fn image(name: &str) -> ImageSource {
+        ImageSource::Path(name.into())
+    }
+
+    /// Starts playing 100 ms transitions, returning the image that is shown and the transitions.
+    async fn transitions() -> (Arc<Mutex<ImageSource>>, Receiver<Option<Transition>>) {
+        let config =
+            serde_json::from_value(json!({ "kind": "crossfade", "duration": 100 })).unwrap();
+        let shown = Arc::new(Mutex::new(image("a")));
+        let current = shown.clone();
+        let transitions = get_transitions(config, move || {
+            current.lock().ok().map(|image| image.clone())
+        })
+        .await;
+        // Lets the transitions see the first image before the test changes it.
+        sleep(FRAME_INTERVAL).await;
+        (shown, transitions)
+    }
+
+    /// Gets the newest transition that was sent.
+    fn latest(transitions: &Receiver<Option<Transition>>) -> Option<Transition> {
+        let mut latest = None;
+        while let Ok(transition) = transitions.try_recv() {
+            latest = transition;
+        }
+        latest
+    }
+
+    /// Receives transitions until one ends, returning the last one that was still playing.
+    async fn play(transitions: &Receiver<Option<Transition>>) -> Transition {
+        let mut last: Option<Transition> = None;
+        while let Some(transition) = transitions.recv().await.unwrap() {
+            if let Some(last) = &last {
+                assert!(transition.progress() > last.progress());
+            }
+            last = Some(transition);
+        }
+        last.unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nothing_plays_until_the_image_changes() {
+        let (_shown, transitions) = transitions().await;
+        sleep(Duration::from_millis(200)).await;
+        assert!(transitions.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_transition_plays_from_the_previous_image_and_then_ends() {
+        let (shown, transitions) = transitions().await;
+        *shown.lock().unwrap() = image("b");
+
+        let first = transitions.recv().await.unwrap().unwrap();
+        assert_eq!(first.previous(), &image("a"));
+        assert!(first.progress() < 0.2, "{}", first.progress());
+        let last = play(&transitions).await;
+        assert!(last.progress() < 1.0);
+
+        sleep(Duration::from_millis(200)).await;
+        assert!(transitions.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn changing_the_image_again_restarts_the_transition() {
+        let (shown, transitions) = transitions().await;
+        *shown.lock().unwrap() = image("b");
+        transitions.recv().await.unwrap().unwrap();
+        sleep(Duration::from_millis(50)).await;
+        let halfway = latest(&transitions).unwrap();
+        assert!(halfway.progress() >= 0.4, "{}", halfway.progress());
+
+        *shown.lock().unwrap() = image("c");
+        let restarted = transitions.recv().await.unwrap().unwrap();
+        assert_eq!(restarted.previous(), &image("b"));
+        assert!(restarted.progress() < 0.2, "{}", restarted.progress());
+        play(&transitions).await;
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use async_channel::Receiver;
+use rdev::EventType;
+use rotatar_types::TypingConfig;
+use serde::Serialize;
+
+use crate::{AfkTimer, interval, keyboard};
+
+/// How often the typing activity is recalculated.
+const TYPING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The smallest change of the key rate, in keystrokes per second, that is sent out. Smaller
+/// changes happen with every keystroke, and would redraw the avatar for nothing.
+const MIN_RATE_CHANGE: f32 = 1.0;
+
+/// Information about how actively the keyboard is being used. This only ever contains timing
+/// information, the keys themselves are never stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct TypingActivity {
+    active: bool,
+    /// Keystrokes per second, measured over the configured rate window.
+    key_rate: f32,
+}
+
+impl TypingActivity {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn key_rate(&self) -> f32 {
+        self.key_rate
+    }
+}
+
+/// Starts listening to global keyboard input, returning a receiver that gets the new typing
+/// activity whenever it changes. Every keystroke also wakes the AFK timer.
+///
+/// Only the time of each key press is sent out of the keyboard hook, so the identity of a key can
+/// never be logged or stored.
+pub async fn get_typing_activity(
+    config: TypingConfig,
+    afk_timer: AfkTimer,
+) -> Receiver<TypingActivity> {
+    let (keystroke_sender, keystroke_receiver) = async_channel::unbounded();
+    let (sender, out_receiver) = async_channel::unbounded();
+
+    keyboard::on_key_event(move |event| {
+        if let EventType::KeyPress(_) = event {
+            let _ = keystroke_sender.try_send(Instant::now());
+        }
+        !keystroke_sender.is_closed()
+    });
+
+    tokio::spawn(async move {
+        let mut keystrokes = Keystrokes::new(&config);
+        let mut last_activity = TypingActivity::default();
+        interval!(TYPING_POLL_INTERVAL, sender, {
+            while let Ok(keystroke) = keystroke_receiver.try_recv() {
+                keystrokes.push(keystroke);
+                afk_timer.wake();
+            }
+            let activity = keystrokes.activity(Instant::now());
+            if changed(&last_activity, &activity) {
+                if sender.send(activity).await.is_err() {
+                    break;
+                }
+                last_activity = activity;
+            }
+        });
+    });
+    out_receiver
+}
+
+/// The keystrokes the typing activity is worked out from.
+struct Keystrokes {
+    timeout: Duration,
+    rate_window: Duration,
+    /// The keystrokes within the rate window, oldest first.
+    recent: VecDeque<Instant>,
+    last: Option<Instant>,
+}
+
+impl Keystrokes {
+    fn new(config: &TypingConfig) -> Self {
+        Self {
+            timeout: Duration::from_millis(config.timeout()),
+            rate_window: Duration::from_millis(config.rate_window().max(1)),
+            recent: VecDeque::new(),
+            last: None,
+        }
+    }
+
+    fn push(&mut self, keystroke: Instant) {
+        self.recent.push_back(keystroke);
+        self.last = Some(keystroke);
+    }
+
+    /// Gets the typing activity at `now`, forgetting the keystrokes that left the rate window.
+    fn activity(&mut self, now: Instant) -> TypingActivity {
+        while self
+            .recent
+            .front()
+            .is_some_and(|keystroke| now.duration_since(*keystroke) > self.rate_window)
+        {
+            self.recent.pop_front();
+        }
+        TypingActivity {
+            active: self
+                .last
+                .is_some_and(|last| now.duration_since(last) <= self.timeout),
+            key_rate: self.recent.len() as f32 / self.rate_window.as_secs_f32(),
+        }
+    }
+}
+
+/// Whether the typing activity changed enough to be sent out. The key rate going to or from zero
+/// always counts, so that it ends up at zero once typing stops.
+fn changed(from: &TypingActivity, to: &TypingActivity) -> bool {
+    from.active != to.active
+        || (from.key_rate == 0.0) != (to.key_rate == 0.0)
+        || (from.key_rate - to.key_rate).abs() >= MIN_RATE_CHANGE
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn keystrokes() -> Keystrokes {
+        let config: TypingConfig =
+            serde_json::from_value(json!({ "timeout": 400, "rate_window": 2000 })).unwrap();
+        Keystrokes::new(&config)
+    }
+
+    fn activity(active: bool, key_rate: f32) -> TypingActivity {
+        TypingActivity { active, key_rate }
+    }
+
+    #[test]
+    fn typing_stays_active_until_the_timeout() {
+        let start = Instant::now();
+        let mut keystrokes = keystrokes();
+        assert!(!keystrokes.activity(start).is_active());
+
+        keystrokes.push(start);
+        assert!(keystrokes.activity(start).is_active());
+        assert!(
+            keystrokes
+                .activity(start + Duration::from_millis(400))
+                .is_active()
+        );
+        assert!(
+            !keystrokes
+                .activity(start + Duration::from_millis(401))
+                .is_active()
+        );
+    }
+
+    #[test]
+    fn the_key_rate_is_measured_over_the_rate_window() {
+        let start = Instant::now();
+        let mut keystrokes = keystrokes();
+        for millis in [0, 500, 1000, 1500] {
+            keystrokes.push(start + Duration::from_millis(millis));
+        }
+        assert_eq!(
+            keystrokes
+                .activity(start + Duration::from_millis(1500))
+                .key_rate(),
+            2.0
+        );
+        assert_eq!(
+            keystrokes
+                .activity(start + Duration::from_millis(2001))
+                .key_rate(),
+            1.5
+        );
+    }
+
+    #[test]
+    fn keystrokes_expire_once_they_leave_the_rate_window() {
+        let start = Instant::now();
+        let mut keystrokes = keystrokes();
+        keystrokes.push(start);
+        keystrokes.push(start + Duration::from_millis(100));
+
+        assert_eq!(
+            keystrokes.activity(start + Duration::from_millis(2200)),
+            activity(false, 0.0)
+        );
+        assert!(keystrokes.recent.is_empty());
+    }
+
+    #[test]
+    fn only_meaningful_changes_are_sent() {
+        assert!(!changed(&activity(true, 2.0), &activity(true, 2.5)));
+        assert!(changed(&activity(true, 2.0), &activity(true, 3.0)));
+        assert!(changed(&activity(true, 2.0), &activity(false, 2.0)));
+        assert!(changed(&activity(false, 0.5), &activity(false, 0.0)));
+        assert!(changed(&activity(false, 0.0), &activity(true, 0.5)));
+        assert!(!changed(&activity(false, 0.0), &activity(false, 0.0)));
+    }
+}
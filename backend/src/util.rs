@@ -5,8 +5,17 @@ macro_rules! arctex {
     };
 }
 
+/// Runs the block at every tick of an interval, forever or until nobody is listening to `$sender`
+/// anymore when it is given.
 #[macro_export]
 macro_rules! interval {
+    ($duration:expr, $sender:expr, $block:block) => {
+        let mut interval = tokio::time::interval($duration);
+        while !$sender.is_closed() {
+            interval.tick().await;
+            $block
+        }
+    };
     ($duration:expr, $block:block) => {
         let mut interval = tokio::time::interval($duration);
         loop {
@@ -15,3 +24,33 @@ macro_rules! interval {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn intervals_without_a_sender_run_until_they_break() {
+        let mut ticks = 0;
+        interval!(Duration::from_millis(10), {
+            ticks += 1;
+            if ticks == 3 {
+                break;
+            }
+        });
+        assert_eq!(ticks, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn intervals_stop_once_nobody_listens() {
+        let (sender, receiver) = async_channel::unbounded::<()>();
+        let mut ticks = 0;
+        interval!(Duration::from_millis(10), sender, {
+            ticks += 1;
+            if ticks == 3 {
+                receiver.close();
+            }
+        });
+        assert_eq!(ticks, 3);
+    }
+}
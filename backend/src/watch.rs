@@ -0,0 +1,268 @@
+use std::{
+    collections::HashSet,
+    mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use async_channel::Receiver;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rotatar_types::{Config, SpriteSheet, ValidArgs};
+
+use crate::{load, props_path};
+
+/// How long the files have to be left alone before the config is loaded again, as editors often
+/// save a file in more than one step.
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+/// Watches the config file and the assets it refers to, returning a receiver that gets the config
+/// again whenever any of them change. Changes that make the config invalid are reported and
+/// skipped, so the last config that loaded stays in use.
+pub async fn get_config_changes(args: ValidArgs, config: &Config) -> Receiver<Config> {
+    let (sender, out_receiver) = async_channel::unbounded();
+    let (event_sender, events) = async_channel::unbounded();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let _ = event_sender.send_blocking(event);
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            log::warn!("Could not watch the config for changes: {error}");
+            return out_receiver;
+        }
+    };
+    let mut watched = WatchedFiles::new(args.config_path(), config);
+    watched.watch(&mut watcher, None);
+
+    tokio::spawn(async move {
+        while let Ok(event) = events.recv().await {
+            if !event.is_ok_and(|event| watched.changed_by(&event)) {
+                continue;
+            }
+            // Wait for the files to settle, dropping whatever else happens to them meanwhile.
+            loop {
+                match tokio::time::timeout(SETTLE_TIME, events.recv()).await {
+                    Ok(Ok(_)) => continue,
+                    Ok(Err(_)) => return,
+                    Err(_) => break,
+                }
+            }
+            match load(&args) {
+                Ok(config) => {
+                    let previous =
+                        mem::replace(&mut watched, WatchedFiles::new(args.config_path(), &config));
+                    watched.watch(&mut watcher, Some(&previous));
+                    if sender.send(config).await.is_err() {
+                        break;
+                    }
+                }
+                Err(error) => log::warn!(
+                    "The changed config was not applied, the last working config is still used: {error}"
+                ),
+            }
+        }
+    });
+    out_receiver
+}
+
+/// The files a config is loaded from, and the directories they are watched through. Directories
+/// are watched instead of the files themselves, as many editors save by replacing the file.
+struct WatchedFiles {
+    files: HashSet<PathBuf>,
+    /// The directory images are discovered in, where any new or removed file matters. It is
+    /// watched along with everything in it, as the file name patterns can include directories.
+    image_directory: Option<PathBuf>,
+    /// The file the shown props are remembered in, which may be in the image directory.
+    props_path: PathBuf,
+    directories: HashSet<PathBuf>,
+}
+
+impl WatchedFiles {
+    fn new(config_path: &Path, config: &Config) -> Self {
+        let mut files: HashSet<PathBuf> = config
+            .image_paths()
+            .into_iter()
+            .chain(config.idle_sheet().into_iter().flat_map(sheet_paths))
+            .chain(config.speaking_sheet().into_iter().flat_map(sheet_paths))
+            .map(|path| absolute(path))
+            .collect();
+        files.insert(absolute(config_path));
+        let image_directory = config
+            .image_directory()
+            .map(|directory| absolute(directory.path()));
+        let directories = files
+            .iter()
+            .filter_map(|file| file.parent())
+            .filter(|directory| {
+                image_directory
+                    .as_ref()
+                    .is_none_or(|image_directory| !directory.starts_with(image_directory))
+            })
+            .map(Path::to_path_buf)
+            .collect();
+        Self {
+            files,
+            image_directory,
+            props_path: absolute(&props_path(config_path)),
+            directories,
+        }
+    }
+
+    /// Whether the event changed any of these files. Loading the config reads the files again,
+    /// so only changes to them count.
+    fn changed_by(&self, event: &Event) -> bool {
+        !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|path| self.contains(path))
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.files.contains(path)
+            || self
+                .image_directory
+                .as_ref()
+                .is_some_and(|directory| path.starts_with(directory) && path != self.props_path)
+    }
+
+    /// Watches the directories of these files, and stops watching the directories of the
+    /// `previous` files that are not needed anymore.
+    fn watch(&self, watcher: &mut RecommendedWatcher, previous: Option<&WatchedFiles>) {
+        let no_directories = HashSet::new();
+        let previous_directories =
+            previous.map_or(&no_directories, |previous| &previous.directories);
+        let previous_image_directory =
+            previous.and_then(|previous| previous.image_directory.as_ref());
+        let unwatched = previous_directories.difference(&self.directories).chain(
+            previous_image_directory
+                .filter(|directory| self.image_directory.as_ref() != Some(*directory)),
+        );
+        for directory in unwatched {
+            let _ = watcher.unwatch(directory);
+        }
+        let watched = self
+            .directories
+            .difference(previous_directories)
+            .map(|directory| (directory, RecursiveMode::NonRecursive))
+            .chain(
+                self.image_directory
+                    .as_ref()
+                    .filter(|directory| previous_image_directory != Some(*directory))
+                    .map(|directory| (directory, RecursiveMode::Recursive)),
+            );
+        for (directory, mode) in watched {
+            if let Err(error) = watcher.watch(directory, mode) {
+                log::warn!(
+                    "Could not watch '{}' for changes: {error}",
+                    directory.display()
+                );
+            }
+        }
+    }
+}
+
+fn sheet_paths(sheet: &SpriteSheet) -> Vec<&PathBuf> {
+    sheet.atlas().into_iter().chain([sheet.path()]).collect()
+}
+
+/// Makes a path absolute, so that it can be compared with the paths changes are reported for.
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::mpsc};
+
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_util;
+
+    /// Creates a directory with an empty image directory in it, also returning its absolute path.
+    /// The directory is removed once the [`TempDir`] is dropped.
+    fn directory() -> (TempDir, PathBuf) {
+        let directory = test_util::directory(&[]);
+        fs::create_dir_all(directory.path().join("images")).unwrap();
+        let path = absolute(directory.path());
+        (directory, path)
+    }
+
+    fn config(directory: &Path, idle: &[&str]) -> Config {
+        let idle: Vec<PathBuf> = idle.iter().map(|name| directory.join(name)).collect();
+        test_util::config(json!({
+            "idle_images": idle,
+            "speaking_images": idle,
+            "image_directory": {
+                "path": directory.join("images"),
+                "idle": "idle-{index}.png",
+                "speaking": "speaking-{index}.png",
+            },
+        }))
+    }
+
+    #[test]
+    fn everything_in_the_image_directory_is_watched_except_the_props_file() {
+        let (_directory, directory) = directory();
+        let watched = WatchedFiles::new(
+            &directory.join("images/config.json"),
+            &config(&directory, &[]),
+        );
+
+        assert!(watched.contains(&directory.join("images/config.json")));
+        assert!(watched.contains(&directory.join("images/idle-0.png")));
+        assert!(watched.contains(&directory.join("images/new/idle-0.png")));
+        assert!(!watched.contains(&directory.join("images/config.props.json")));
+        assert!(!watched.contains(&directory.join("other.png")));
+    }
+
+    #[test]
+    fn only_the_files_of_the_config_are_watched_outside_the_image_directory() {
+        let (_directory, directory) = directory();
+        let watched = WatchedFiles::new(
+            &directory.join("config.json"),
+            &config(&directory, &["idle.png"]),
+        );
+
+        assert!(watched.contains(&directory.join("config.json")));
+        assert!(watched.contains(&directory.join("idle.png")));
+        assert!(!watched.contains(&directory.join("config.props.json")));
+        assert!(!watched.contains(&directory.join("speaking.png")));
+    }
+
+    #[test]
+    fn directories_in_the_image_directory_are_left_to_it() {
+        let (_directory, directory) = directory();
+        let watched = WatchedFiles::new(
+            &directory.join("config.json"),
+            &config(&directory, &["idle.png", "images/idle-0.png"]),
+        );
+
+        assert_eq!(watched.directories, HashSet::from([directory.clone()]));
+        assert_eq!(watched.image_directory, Some(directory.join("images")));
+    }
+
+    #[test]
+    fn writing_the_props_file_is_not_a_change() {
+        let (_directory, directory) = directory();
+        let config_path = directory.join("images/config.json");
+        let watched = WatchedFiles::new(&config_path, &config(&directory, &[]));
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let _ = sender.send(event);
+        })
+        .unwrap();
+        watched.watch(&mut watcher, None);
+
+        let changed = |path: &Path| {
+            fs::write(path, "[]").unwrap();
+            let mut changed = false;
+            while let Ok(event) = receiver.recv_timeout(Duration::from_millis(500)) {
+                changed |= event.is_ok_and(|event| watched.changed_by(&event));
+            }
+            changed
+        };
+        assert!(!changed(&props_path(&config_path)));
+        assert!(changed(&config_path));
+        assert!(changed(&directory.join("images/idle-0.png")));
+    }
+}
@@ -0,0 +1,101 @@
+use proptest::prelude::*;
+use rotatar_backend::State;
+use rotatar_types::TwoInts;
+
+fn state(screen_size: (i32, i32), sections: (i32, i32)) -> State {
+    let (message_sender, _) = async_channel::unbounded();
+    let (audio_sender, audio_receiver) = async_channel::bounded(1);
+    State::new(
+        message_sender,
+        TwoInts::from(screen_size),
+        sections,
+        audio_sender,
+        audio_receiver,
+    )
+}
+
+/// A valid screen size together with a section grid that fits inside of it.
+fn screen_and_sections() -> impl Strategy<Value = ((i32, i32), (i32, i32))> {
+    (1..=64i32, 1..=64i32).prop_flat_map(|sections| {
+        (
+            (sections.0..=7680, sections.1..=4320).prop_map(|size| (size.0, size.1)),
+            Just(sections),
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn any_position_maps_to_a_valid_section(
+        (screen_size, sections) in screen_and_sections(),
+        x in any::<i32>(),
+        y in any::<i32>(),
+    ) {
+        let mut state = state(screen_size, sections);
+        state.set_current_image_xy(x, y);
+        prop_assert!(state.current_image() < state.total_sections());
+
+        let (section_x, section_y) = state.section_xy(x, y);
+        prop_assert!((0..sections.0).contains(&section_x));
+        prop_assert!((0..sections.1).contains(&section_y));
+        prop_assert_eq!(
+            state.current_image(),
+            (section_y * sections.0 + section_x) as usize
+        );
+    }
+
+    #[test]
+    fn positions_on_screen_are_not_outside(
+        (screen_size, sections) in screen_and_sections(),
+        x_fraction in 0.0..1.0f64,
+        y_fraction in 0.0..1.0f64,
+    ) {
+        let x = (screen_size.0 as f64 * x_fraction) as i32;
+        let y = (screen_size.1 as f64 * y_fraction) as i32;
+        let mut state = state(screen_size, sections);
+        state.set_current_image_xy(x, y);
+        prop_assert!(!state.is_cursor_outside());
+    }
+
+    #[test]
+    fn positions_off_screen_are_clamped_to_the_edge(
+        (screen_size, sections) in screen_and_sections(),
+        x in any::<i32>(),
+        y in any::<i32>(),
+    ) {
+        let x_outside = x < 0 || x >= screen_size.0;
+        let y_outside = y < 0 || y >= screen_size.1;
+        let mut state = state(screen_size, sections);
+        state.set_current_image_xy(x, y);
+        prop_assert_eq!(state.is_cursor_outside(), x_outside || y_outside);
+
+        let (section_x, section_y) = state.section_xy(x, y);
+        if x < 0 {
+            prop_assert_eq!(section_x, 0);
+        } else if x >= screen_size.0 {
+            prop_assert_eq!(section_x, sections.0 - 1);
+        }
+        if y < 0 {
+            prop_assert_eq!(section_y, 0);
+        } else if y >= screen_size.1 {
+            prop_assert_eq!(section_y, sections.1 - 1);
+        }
+    }
+
+    #[test]
+    fn set_current_image_only_accepts_existing_sections(
+        (screen_size, sections) in screen_and_sections(),
+        index in any::<usize>(),
+    ) {
+        let mut state = state(screen_size, sections);
+        let previous = state.current_image();
+        let changed = state.set_current_image(index);
+        if index < state.total_sections() && index != previous {
+            prop_assert!(changed);
+            prop_assert_eq!(state.current_image(), index);
+        } else {
+            prop_assert!(!changed);
+            prop_assert_eq!(state.current_image(), previous);
+        }
+    }
+}
@@ -1,20 +1,24 @@
-use std::{
-    path::PathBuf,
-    sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex};
 
 use async_channel::{Receiver, Sender};
 use iced::{
-    Background, Subscription, Task,
+    Background, Padding, Subscription, Task,
     futures::{SinkExt, Stream},
-    stream, widget,
+    stream,
+    widget::{self, image},
+    window,
 };
 use rotatar_backend::{
     Message, State, arctex,
     audio::{self, AudioMessage, AudioStatus},
-    set_state,
+    save_props, set_state,
+};
+use rotatar_types::{Anchor, Config, FitMode, ImageSource, Transform, TransitionKind};
+
+use crate::{
+    avatar::{self, Avatar},
+    cache::ImageCache,
 };
-use rotatar_types::Config;
 
 macro_rules! audio_section {
     ($state:expr) => {
@@ -31,16 +35,33 @@ macro_rules! audio_section {
     };
 }
 
+/// An image that is part of the avatar, together with how it is drawn.
+struct DrawnImage {
+    source: ImageSource,
+    frame: usize,
+    opacity: f32,
+    /// How far the image is moved to the right, relative to its width.
+    shift: f32,
+    /// How far the image is moved right and down, in pixels of the avatar's images.
+    offset: (f32, f32),
+}
+
 pub struct App {
     config: Arc<Mutex<Config>>,
     receiver: Arc<Receiver<Message>>,
     state: Arc<Mutex<State>>,
+    /// The images of the current config, which are swapped out whole when the config is reloaded.
+    images: Arc<Mutex<Arc<ImageCache>>>,
+    /// The size of the window in logical pixels, once it is known.
+    window_size: Option<(f32, f32)>,
+    scale_factor: f32,
     background_color: iced::Color,
 }
 
 impl App {
     pub fn new(
         config: Config,
+        images: ImageCache,
         background_color: iced::Color,
         receiver: Receiver<Message>,
         message_sender: Sender<Message>,
@@ -59,6 +80,9 @@ impl App {
                 audio_sender,
                 audio_receiver
             )),
+            images: arctex!(Arc::new(images)),
+            window_size: None,
+            scale_factor: 1.0,
             background_color,
         }
     }
@@ -86,6 +110,47 @@ impl App {
             Message::SensitivityChanged(sensitivity) => {
                 set_state!(self.state, set_sensitivity, sensitivity);
             }
+            Message::MagnitudeChanged(magnitude) => {
+                set_state!(self.state, set_magnitude, magnitude);
+            }
+            Message::SetExpression(expression) => {
+                let known = match (&expression, self.config.lock()) {
+                    (Some(name), Ok(config)) => config.expression(name).is_some(),
+                    _ => true,
+                };
+                if known {
+                    set_state!(self.state, set_expression, expression);
+                } else {
+                    log::warn!("There is no expression called {expression:?}");
+                }
+            }
+            Message::SetProp(name, shown) => {
+                let known = self
+                    .config
+                    .lock()
+                    .is_ok_and(|config| config.prop(&name).is_some());
+                if known {
+                    let saved = self.state.lock().ok().and_then(|mut state| {
+                        state.set_prop(name, shown).then(|| state.saved_props())?
+                    });
+                    if let Some((path, props)) = saved {
+                        save_props(&path, &props);
+                    }
+                } else {
+                    log::warn!("There is no prop called '{name}'");
+                }
+            }
+            Message::WindowResized(width, height) => {
+                self.window_size = Some((width, height));
+                // Resizing is the only time winit reports a new scale factor to iced, so this is
+                // when it is checked for.
+                return window::get_latest()
+                    .and_then(window::get_scale_factor)
+                    .map(Message::ScaleFactorChanged);
+            }
+            Message::ScaleFactorChanged(scale_factor) => {
+                self.scale_factor = scale_factor;
+            }
             _ => {}
         }
         Task::none()
@@ -93,9 +158,35 @@ impl App {
 
     pub fn view(&self) -> iced::Element<'_, Message> {
         if let Ok(state) = self.state.lock() {
-            let row = widget::row![widget::image(self.get_current_image(&state))];
-            let column = widget::column![row, audio_section!(state)];
-            widget::center(column)
+            let images = self.get_current_images(&state);
+            // The images are swapped together with the state, so they are taken while it is
+            // locked to match it.
+            let cache = self
+                .images
+                .lock()
+                .map(|cache| cache.clone())
+                .map_err(|error| error.to_string());
+            let (current_image, transform) = (state.current_image(), state.transform());
+            // The audio section is drawn over the avatar, so that the avatar can use the whole
+            // window.
+            let audio =
+                widget::container(widget::container(audio_section!(state)).max_width(300.0))
+                    .width(iced::Length::Fill)
+                    .height(iced::Length::Fill)
+                    .padding(10.0)
+                    .align_x(iced::alignment::Horizontal::Right)
+                    .align_y(iced::alignment::Vertical::Bottom);
+            // Drawing the avatar may decode images, which shouldn't hold up the loops that change
+            // the state.
+            drop(state);
+            let image: iced::Element<'_, Message> = match cache {
+                _ if images.is_empty() => {
+                    widget::center(widget::text!("No image for section {current_image}")).into()
+                }
+                Ok(cache) => self.draw_avatar(&cache, images, transform),
+                Err(error) => widget::center(widget::text(error)).into(),
+            };
+            widget::container(widget::stack![image, audio])
                 .style(|_| {
                     widget::container::Style::default()
                         .background(Background::Color(self.background_color))
@@ -117,25 +208,165 @@ impl App {
         self.config.clone()
     }
 
-    fn get_current_image(&self, state: &State) -> PathBuf {
-        match self.config.lock() {
-            Ok(config) => {
-                if state.is_speaking() {
-                    config
-                        .speaking_images()
-                        .get(state.current_image())
-                        .expect("There should be an image")
-                        .clone()
-                } else {
-                    config
-                        .idle_images()
-                        .get(state.current_image())
-                        .expect("There should be an image")
-                        .clone()
-                }
+    pub fn images(&self) -> Arc<Mutex<Arc<ImageCache>>> {
+        self.images.clone()
+    }
+
+    /// Gets the images that make up the avatar, from bottom to top. Blended sections are stacked
+    /// so that each ends up with its weight, any transition is drawn over them and layers are
+    /// drawn on top, followed by the props that are shown. The image being transitioned away from
+    /// shows the first frame of animated images.
+    fn get_current_images(&self, state: &State) -> Vec<DrawnImage> {
+        // A poisoned config is reported by the view, which has nothing to draw then.
+        let Ok(config) = self.config.lock() else {
+            return Vec::new();
+        };
+        let transition = config.transition();
+        let (previous_shift, current_shift) = match state.transition() {
+            Some(playing) if transition.kind() == TransitionKind::Slide => {
+                (-playing.progress(), 1.0 - playing.progress())
+            }
+            _ => (0.0, 0.0),
+        };
+        let mut images = Vec::new();
+        let mut total_weight = 0.0;
+        for (source, weight) in state.current_blend(&config) {
+            total_weight += weight;
+            images.push(DrawnImage {
+                source: source.clone(),
+                frame: state.frame(source),
+                opacity: weight / total_weight,
+                shift: current_shift,
+                offset: (0.0, 0.0),
+            });
+        }
+        if let Some(playing) = state.transition() {
+            let opacity = match transition.kind() {
+                TransitionKind::Crossfade => 1.0 - playing.progress(),
+                _ => 1.0,
+            };
+            images.push(DrawnImage {
+                source: playing.previous().clone(),
+                frame: 0,
+                opacity,
+                shift: previous_shift,
+                offset: (0.0, 0.0),
+            });
+        }
+        for (source, offset) in state
+            .current_layer_sources(&config)
+            .into_iter()
+            .chain(state.current_prop_sources(&config))
+        {
+            images.push(DrawnImage {
+                source: source.clone(),
+                frame: state.frame(source),
+                opacity: 1.0,
+                shift: 0.0,
+                offset,
+            });
+        }
+        images
+    }
+
+    /// Draws the images of the avatar on top of each other, scaled and placed in the window the way
+    /// the window config asks for. All images are scaled by the same amount, so that layers stay
+    /// lined up, based on the largest of them.
+    fn draw_avatar(
+        &self,
+        cache: &ImageCache,
+        images: Vec<DrawnImage>,
+        transform: Transform,
+    ) -> iced::Element<'_, Message> {
+        let (window, effects) = match self.config.lock() {
+            Ok(config) => (config.window(), config.effects()),
+            Err(error) => return widget::center(widget::text(error.to_string())).into(),
+        };
+        let avatar_size = images
+            .iter()
+            .filter_map(|drawn| cache.size(&drawn.source))
+            .fold(
+                (0.0f32, 0.0f32),
+                |(width, height), (image_width, image_height)| {
+                    (
+                        width.max(image_width as f32),
+                        height.max(image_height as f32),
+                    )
+                },
+            );
+        let filter_method = if window.fit() == FitMode::IntegerNearest {
+            image::FilterMethod::Nearest
+        } else {
+            image::FilterMethod::Linear
+        };
+
+        let scale = window.scale_for(avatar_size, self.window_size.unwrap_or(avatar_size));
+        let mut errors = Vec::new();
+        let mut layers = Vec::new();
+        for drawn in images {
+            match cache.get(&drawn.source, drawn.frame, scale * self.scale_factor) {
+                Ok(image) => layers.push((drawn, image)),
+                Err(error) => errors.push(error),
             }
-            Err(error) => todo!("{error}"),
         }
+        if layers.is_empty() {
+            return widget::center(widget::text(errors.join("\n"))).into();
+        }
+
+        // The outlines and shadows of all images are drawn below the images, so that they don't
+        // cover the images below them. They are larger than the images by their margin, and share
+        // their centers.
+        let mut margin = 0.0f32;
+        let mut outlines = Vec::new();
+        let mut images = Vec::new();
+        for (drawn, cached) in layers {
+            let (width, height) = cached.size.unwrap_or(avatar_size);
+            let shift = drawn.shift_by(width, scale);
+            let rendered = cached
+                .size
+                .and_then(|_| cache.effects(&drawn.source, drawn.frame, &cached, &effects));
+            if let Some((handle, effects_margin)) = rendered {
+                margin = margin.max(effects_margin);
+                outlines.push(avatar::Layer {
+                    handle,
+                    size: iced::Size::new(
+                        width + effects_margin * 2.0,
+                        height + effects_margin * 2.0,
+                    ) * scale,
+                    shift,
+                    opacity: drawn.opacity,
+                });
+            }
+            images.push(avatar::Layer {
+                handle: cached.handle,
+                size: iced::Size::new(width, height) * scale,
+                shift,
+                opacity: drawn.opacity,
+            });
+        }
+        outlines.extend(images);
+        let stack = Avatar::new(
+            outlines,
+            iced::Size::new(avatar_size.0, avatar_size.1) * scale,
+            margin * scale,
+            transform,
+            filter_method,
+        );
+        // Moving the avatar up is done by padding below it, and the other way around. The
+        // avatar is centered, so it only moves by half of the padding.
+        let avatar = widget::container(stack).padding(
+            Padding::ZERO
+                .top(transform.offset().min(0.0).abs() * 2.0)
+                .bottom(transform.offset().max(0.0) * 2.0),
+        );
+        widget::container(avatar)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .padding(window.padding())
+            .align_x(horizontal_alignment(window.anchor()))
+            .align_y(vertical_alignment(window.anchor()))
+            .clip(true)
+            .into()
     }
 
     fn state_updater() -> impl Stream<Item = Message> {
@@ -155,7 +386,11 @@ impl App {
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
-        Subscription::run(Self::state_updater)
+        Subscription::batch([
+            Subscription::run(Self::state_updater),
+            window::resize_events()
+                .map(|(_, size)| Message::WindowResized(size.width, size.height)),
+        ])
     }
 
     /// Gets a clone of this app's [Arc](std::sync::Arc)<[Mutex](std::sync::Mutex)<[State](crate::state::State)>>
@@ -163,3 +398,34 @@ impl App {
         self.state.clone()
     }
 }
+
+fn horizontal_alignment(anchor: Anchor) -> iced::alignment::Horizontal {
+    if anchor.horizontal() < 0.5 {
+        iced::alignment::Horizontal::Left
+    } else if anchor.horizontal() > 0.5 {
+        iced::alignment::Horizontal::Right
+    } else {
+        iced::alignment::Horizontal::Center
+    }
+}
+
+fn vertical_alignment(anchor: Anchor) -> iced::alignment::Vertical {
+    if anchor.vertical() < 0.5 {
+        iced::alignment::Vertical::Top
+    } else if anchor.vertical() > 0.5 {
+        iced::alignment::Vertical::Bottom
+    } else {
+        iced::alignment::Vertical::Center
+    }
+}
+
+impl DrawnImage {
+    /// How far the image is moved right and down in logical pixels, for an image that is `width`
+    /// wide and drawn at `scale`.
+    fn shift_by(&self, width: f32, scale: f32) -> iced::Vector {
+        iced::Vector::new(
+            (width * self.shift + self.offset.0) * scale,
+            self.offset.1 * scale,
+        )
+    }
+}
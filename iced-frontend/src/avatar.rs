@@ -0,0 +1,128 @@
+use iced::{
+    Element, Length, Radians, Rectangle, Size, Vector,
+    advanced::{
+        Layout, Widget,
+        image::{self, FilterMethod, Handle},
+        layout, mouse, renderer,
+        widget::Tree,
+    },
+};
+use rotatar_types::Transform;
+
+/// The images of the avatar drawn on top of each other. The whole avatar is scaled and rotated
+/// around its bottom center, so that the images stay lined up however it is moved.
+pub struct Avatar {
+    layers: Vec<Layer>,
+    /// The size of the avatar's images before they are transformed, in logical pixels.
+    size: Size,
+    /// Extra room around the images, in logical pixels, that their effects are drawn in.
+    margin: f32,
+    transform: Transform,
+    filter_method: FilterMethod,
+}
+
+/// One image of the [Avatar].
+pub struct Layer {
+    pub handle: Handle,
+    /// The size the image is drawn at before it is transformed, in logical pixels.
+    pub size: Size,
+    /// How far the center of the image is from the center of the avatar, in logical pixels.
+    pub shift: Vector,
+    pub opacity: f32,
+}
+
+impl Avatar {
+    pub fn new(
+        layers: Vec<Layer>,
+        size: Size,
+        margin: f32,
+        transform: Transform,
+        filter_method: FilterMethod,
+    ) -> Self {
+        Self {
+            layers,
+            size,
+            margin,
+            transform,
+            filter_method,
+        }
+    }
+
+    /// Where the center of a layer ends up once the avatar is transformed, relative to the center
+    /// of the avatar.
+    fn transformed_center(&self, layer: &Layer) -> Vector {
+        let pivot = Vector::new(0.0, self.size.height / 2.0);
+        let from_pivot = layer.shift - pivot;
+        let (x, y) = (
+            from_pivot.x * self.transform.scale_x(),
+            from_pivot.y * self.transform.scale_y(),
+        );
+        let (sin, cos) = self.transform.rotation().to_radians().sin_cos();
+        pivot + Vector::new(x * cos - y * sin, x * sin + y * cos)
+    }
+}
+
+impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer> for Avatar
+where
+    Renderer: image::Renderer<Handle = Handle>,
+{
+    fn size(&self) -> Size<Length> {
+        Size::new(
+            Length::Fixed(self.size.width + self.margin * 2.0),
+            Length::Fixed(self.size.height + self.margin * 2.0),
+        )
+    }
+
+    fn layout(
+        &self,
+        _tree: &mut Tree,
+        _renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let size = Widget::<Message, Theme, Renderer>::size(self);
+        layout::Node::new(limits.resolve(size.width, size.height, Size::ZERO))
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let center = layout.bounds().center();
+        for layer in &self.layers {
+            let size = Size::new(
+                layer.size.width * self.transform.scale_x(),
+                layer.size.height * self.transform.scale_y(),
+            );
+            let layer_center = center + self.transformed_center(layer);
+            let bounds = Rectangle::new(
+                layer_center - Vector::new(size.width / 2.0, size.height / 2.0),
+                size,
+            );
+            renderer.draw_image(
+                image::Image {
+                    handle: layer.handle.clone(),
+                    filter_method: self.filter_method,
+                    rotation: Radians(self.transform.rotation().to_radians()),
+                    opacity: layer.opacity,
+                    snap: true,
+                },
+                bounds,
+            );
+        }
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<Avatar> for Element<'a, Message, Theme, Renderer>
+where
+    Renderer: image::Renderer<Handle = Handle> + 'a,
+{
+    fn from(avatar: Avatar) -> Self {
+        Element::new(avatar)
+    }
+}
@@ -0,0 +1,523 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use async_channel::Sender;
+use iced::{advanced::graphics, widget::image};
+use rotatar_backend::{Animations, FrameImage, Message, effects, svg};
+use rotatar_types::{Config, EffectsConfig, ImageSource};
+
+/// Every image the avatar can show, decoded ahead of time so that drawing never touches the disk.
+///
+/// Frames of animations and sprite sheet cells are always kept, as the backend has already decoded
+/// them within its own limit. Still images and the frames of frame sequences are decoded at
+/// startup until the memory budget is used up. The rest are decoded when they are first shown,
+/// dropping the least recently shown of them to stay within the budget. SVG images are rasterized
+/// at the size they are shown at in the background, and again whenever that size changes.
+pub struct ImageCache {
+    frames: HashMap<ImageSource, Vec<CachedFrame>>,
+    /// The size of every image, so that images can be scaled before they are decoded.
+    sizes: HashMap<ImageSource, (u32, u32)>,
+    stills: Mutex<Stills>,
+    rasters: Rasters,
+    /// The outline and shadow of each frame of each image source, rendered when first drawn.
+    effects: Mutex<HashMap<(ImageSource, usize), RenderedEffects>>,
+    /// The most memory, in bytes, that decoded still images may use.
+    budget: usize,
+}
+
+/// An image that is ready to be drawn, together with its size in logical pixels before it is
+/// scaled, if that is known.
+pub struct CachedImage {
+    pub handle: image::Handle,
+    pub size: Option<(f32, f32)>,
+}
+
+enum CachedFrame {
+    Handle(image::Handle),
+    /// A frame of a frame sequence, which is decoded like a still image.
+    File(PathBuf),
+}
+
+struct RenderedEffects {
+    /// The size of the image the effects were rendered for, which only changes for SVG images.
+    size: (u32, u32),
+    rendered: Option<(image::Handle, f32)>,
+}
+
+/// The last rasterization of each SVG image, or why it could not be rasterized. Rasterizing runs
+/// on its own thread, and the old raster is drawn until it is done.
+struct Rasters {
+    rasters: Arc<Mutex<HashMap<PathBuf, Result<Raster, String>>>>,
+    /// Makes the app draw again once a rasterization is done.
+    redraw: Sender<Message>,
+}
+
+struct Raster {
+    /// The size the image is meant to be shown at, so that the file isn't parsed again to find it.
+    intrinsic: (u32, u32),
+    /// The size the image was rasterized at, in physical pixels.
+    size: (u32, u32),
+    handle: image::Handle,
+    /// The size the image is being rasterized at, if it is. Only one rasterization runs at a time,
+    /// so that resizing the window doesn't start one for every step of the resize.
+    pending: Option<(u32, u32)>,
+    /// The last size the image could not be rasterized at, so that it isn't tried again.
+    failed: Option<(u32, u32)>,
+}
+
+#[derive(Default)]
+struct Stills {
+    loaded: HashMap<PathBuf, LoadedStill>,
+    /// Images that could not be decoded, with the reason why. These are kept so that each image
+    /// is only tried and reported once.
+    failed: HashMap<PathBuf, String>,
+    used: usize,
+    /// Counts up whenever an image is shown, to find the least recently shown image.
+    clock: u64,
+}
+
+struct LoadedStill {
+    handle: image::Handle,
+    bytes: usize,
+    last_shown: u64,
+}
+
+impl ImageCache {
+    pub fn new(
+        config: &Config,
+        animations: &Animations,
+        budget: usize,
+        redraw: Sender<Message>,
+    ) -> Self {
+        let frames = animations
+            .iter()
+            .map(|(source, animation)| {
+                let handles = animation
+                    .frames()
+                    .iter()
+                    .map(|frame| match frame.image() {
+                        FrameImage::Decoded { image, .. } => {
+                            CachedFrame::Handle(image::Handle::from_rgba(
+                                image.width(),
+                                image.height(),
+                                image.as_raw().clone(),
+                            ))
+                        }
+                        FrameImage::File(path) => CachedFrame::File(path.clone()),
+                    })
+                    .collect();
+                (source.clone(), handles)
+            })
+            .collect();
+        let sizes = config
+            .image_sources()
+            .into_iter()
+            .filter_map(|source| Some((source.clone(), animations.size(source)?)))
+            .collect();
+        let cache = Self {
+            frames,
+            sizes,
+            stills: Mutex::new(Stills::default()),
+            rasters: Rasters::new(redraw),
+            effects: Mutex::new(HashMap::new()),
+            budget,
+        };
+        if let Ok(mut stills) = cache.stills.lock() {
+            let paths = config.image_sources().into_iter().flat_map(|source| {
+                match (source, animations.get(source)) {
+                    (ImageSource::Path(_), None) | (ImageSource::Sequence(_), _) => source.paths(),
+                    _ => Vec::new(),
+                }
+            });
+            for path in paths {
+                // SVG images are rasterized at their own size first, so that there is always a
+                // raster to draw while they are rasterized at the size they are shown at.
+                if svg::is_svg(path) {
+                    cache.rasters.load(path);
+                } else if stills.used < budget {
+                    let _ = stills.load(path, budget);
+                }
+            }
+        }
+        cache
+    }
+
+    pub fn size(&self, source: &ImageSource) -> Option<(u32, u32)> {
+        self.sizes.get(source).copied()
+    }
+
+    /// Gets a frame of an image source, decoding or rasterizing it first if needed. `scale` is how
+    /// many physical pixels each logical pixel of the image ends up drawn at. The error explains
+    /// why the image can't be shown.
+    pub fn get(
+        &self,
+        source: &ImageSource,
+        frame: usize,
+        scale: f32,
+    ) -> Result<CachedImage, String> {
+        let size = self
+            .size(source)
+            .map(|(width, height)| (width as f32, height as f32));
+        let path = match (self.frames.get(source), source) {
+            (Some(frames), _) => match &frames[frame % frames.len()] {
+                CachedFrame::Handle(handle) => {
+                    return Ok(CachedImage {
+                        handle: handle.clone(),
+                        size,
+                    });
+                }
+                CachedFrame::File(path) => path,
+            },
+            (None, ImageSource::Path(path)) => path,
+            (None, ImageSource::Sequence(_) | ImageSource::Cell(_)) => {
+                return Err(format!("{source:?} could not be loaded"));
+            }
+        };
+        if svg::is_svg(path) {
+            return self.rasters.get(path, scale);
+        }
+        let handle = match self.stills.lock() {
+            Ok(mut stills) => stills.load(path, self.budget)?,
+            Err(_) => return Err(String::from("The image cache was poisoned")),
+        };
+        Ok(CachedImage { handle, size })
+    }
+
+    /// Gets the outline and shadow of a frame of an image source, which was gotten with
+    /// [`Self::get`]. The image of the effects is larger than the image by the margin of the
+    /// effects on each side, which is returned with it in logical pixels. Returns `None` if there
+    /// are no effects, or they could not be rendered.
+    pub fn effects(
+        &self,
+        source: &ImageSource,
+        frame: usize,
+        image: &CachedImage,
+        effects: &EffectsConfig,
+    ) -> Option<(image::Handle, f32)> {
+        if !effects.is_drawn() {
+            return None;
+        }
+        let mut rendered = self.effects.lock().ok()?;
+        let key = (source.clone(), frame);
+        let (width, height, pixels) = match &image.handle {
+            image::Handle::Rgba {
+                width,
+                height,
+                pixels,
+                ..
+            } => (*width, *height, pixels.to_vec()),
+            handle => {
+                if let Some(cached) = rendered.get(&key) {
+                    return cached.rendered.clone();
+                }
+                let decoded = graphics::image::load(handle).ok()?;
+                (
+                    decoded.width(),
+                    decoded.height(),
+                    decoded.into_raw().to_vec(),
+                )
+            }
+        };
+        if let Some(cached) = rendered
+            .get(&key)
+            .filter(|cached| cached.size == (width, height))
+        {
+            return cached.rendered.clone();
+        }
+        // SVG images are rasterized at a different size than the one the effects are set for.
+        let scale = image
+            .size
+            .map_or(1.0, |(logical_width, _)| width as f32 / logical_width);
+        let margin = effects::margin(effects, scale) as f32 / scale;
+        let handle = effects::render((width, height), &pixels, effects, scale).map(|effects| {
+            let handle =
+                image::Handle::from_rgba(effects.width(), effects.height(), effects.into_raw());
+            (handle, margin)
+        });
+        rendered.insert(
+            key,
+            RenderedEffects {
+                size: (width, height),
+                rendered: handle.clone(),
+            },
+        );
+        handle
+    }
+}
+
+impl Rasters {
+    fn new(redraw: Sender<Message>) -> Self {
+        Self {
+            rasters: Arc::new(Mutex::new(HashMap::new())),
+            redraw,
+        }
+    }
+
+    /// Rasterizes an SVG image at the size it is meant to be shown at.
+    fn load(&self, path: &Path) {
+        let raster = svg::size(path).and_then(|intrinsic| {
+            let image = svg::rasterize(path, intrinsic)?;
+            Ok(Raster {
+                intrinsic,
+                size: intrinsic,
+                handle: image::Handle::from_rgba(intrinsic.0, intrinsic.1, image.into_raw()),
+                pending: None,
+                failed: None,
+            })
+        });
+        if let Err(error) = &raster {
+            log::warn!("{error}");
+        }
+        if let Ok(mut rasters) = self.rasters.lock() {
+            rasters.insert(path.to_path_buf(), raster);
+        }
+    }
+
+    /// Gets the last rasterization of an SVG image, starting to rasterize it again in the
+    /// background if it was rasterized at a different scale than the one it is drawn at.
+    fn get(&self, path: &Path, scale: f32) -> Result<CachedImage, String> {
+        let mut rasters = self
+            .rasters
+            .lock()
+            .map_err(|_| String::from("The image cache was poisoned"))?;
+        let raster = match rasters.get_mut(path) {
+            Some(Ok(raster)) => raster,
+            Some(Err(error)) => return Err(error.clone()),
+            None => return Err(format!("'{}' was not rasterized", path.display())),
+        };
+        let size = (raster.intrinsic.0 as f32, raster.intrinsic.1 as f32);
+        // Images drawn larger than the largest raster are scaled up from it instead.
+        let scale = scale.min(svg::MAX_SIZE as f32 / size.0.max(size.1));
+        let physical = (
+            ((size.0 * scale).round() as u32).max(1),
+            ((size.1 * scale).round() as u32).max(1),
+        );
+        if raster.size != physical && raster.pending.is_none() && raster.failed != Some(physical) {
+            raster.pending = Some(physical);
+            let rasters = self.rasters.clone();
+            let redraw = self.redraw.clone();
+            let path = path.to_path_buf();
+            thread::spawn(move || {
+                let image = svg::rasterize(&path, physical);
+                let mut rasters = rasters.lock();
+                let raster = rasters
+                    .as_mut()
+                    .ok()
+                    .and_then(|rasters| rasters.get_mut(&path)?.as_mut().ok());
+                if let Some(raster) = raster {
+                    raster.pending = None;
+                    match image {
+                        Ok(image) => {
+                            raster.size = physical;
+                            raster.handle =
+                                image::Handle::from_rgba(physical.0, physical.1, image.into_raw());
+                        }
+                        Err(error) => {
+                            log::warn!("{error}");
+                            raster.failed = Some(physical);
+                        }
+                    }
+                }
+                drop(rasters);
+                // The scale may have changed again in the meantime, which drawing again picks up.
+                let _ = redraw.send_blocking(Message::CurrentImageChanged);
+            });
+        }
+        Ok(CachedImage {
+            handle: raster.handle.clone(),
+            size: Some(size),
+        })
+    }
+}
+
+impl Stills {
+    fn load(&mut self, path: &Path, budget: usize) -> Result<image::Handle, String> {
+        self.clock += 1;
+        if let Some(loaded) = self.loaded.get_mut(path) {
+            loaded.last_shown = self.clock;
+            return Ok(loaded.handle.clone());
+        }
+        if let Some(error) = self.failed.get(path) {
+            return Err(error.clone());
+        }
+        let decoded = match decode(path) {
+            Ok(decoded) => decoded,
+            Err(error) => {
+                self.failed.insert(path.to_path_buf(), error.clone());
+                return Err(error);
+            }
+        };
+        Ok(self.insert(path, decoded, budget))
+    }
+
+    /// Keeps a decoded image as recently shown, dropping older images if it goes over the budget.
+    fn insert(&mut self, path: &Path, decoded: Decoded, budget: usize) -> image::Handle {
+        self.loaded.insert(
+            path.to_path_buf(),
+            LoadedStill {
+                handle: decoded.handle.clone(),
+                bytes: decoded.bytes,
+                last_shown: self.clock,
+            },
+        );
+        self.used += decoded.bytes;
+        self.evict(path, budget);
+        decoded.handle
+    }
+
+    /// Drops the least recently shown images until the budget is kept, never dropping `keep`.
+    fn evict(&mut self, keep: &Path, budget: usize) {
+        while self.used > budget {
+            let Some(oldest) = self
+                .loaded
+                .iter()
+                .filter(|(path, _)| path.as_path() != keep)
+                .min_by_key(|(_, loaded)| loaded.last_shown)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = self.loaded.remove(&oldest) {
+                self.used -= evicted.bytes;
+            }
+        }
+    }
+}
+
+/// An image decoded into raw pixels, which take up `bytes` of memory.
+struct Decoded {
+    handle: image::Handle,
+    bytes: usize,
+}
+
+fn decode(path: &Path) -> Result<Decoded, String> {
+    let decoded = graphics::image::load(&image::Handle::from_path(path)).map_err(|error| {
+        let error = format!("Could not load '{}': {error}", path.display());
+        log::warn!("{error}");
+        error
+    })?;
+    let (width, height) = (decoded.width(), decoded.height());
+    let pixels = decoded.into_raw();
+    Ok(Decoded {
+        bytes: pixels.len(),
+        handle: image::Handle::from_rgba(width, height, pixels),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use iced::advanced::graphics::image::image_rs::RgbaImage;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Writes 2x2 images, which take up 16 bytes each once decoded, into a new directory that is
+    /// removed again once it is dropped.
+    fn images(names: &[&str]) -> (TempDir, Vec<PathBuf>) {
+        let directory = tempfile::tempdir().unwrap();
+        let paths = names
+            .iter()
+            .map(|name| {
+                let path = directory.path().join(format!("{name}.png"));
+                RgbaImage::new(2, 2).save(&path).unwrap();
+                path
+            })
+            .collect();
+        (directory, paths)
+    }
+
+    fn loaded(stills: &Stills) -> Vec<&str> {
+        let mut loaded: Vec<_> = stills
+            .loaded
+            .keys()
+            .map(|path| path.file_stem().unwrap().to_str().unwrap())
+            .collect();
+        loaded.sort();
+        loaded
+    }
+
+    #[test]
+    fn the_least_recently_shown_image_is_dropped() {
+        let (_directory, paths) = images(&["a", "b", "c"]);
+        let mut stills = Stills::default();
+        stills.load(&paths[0], 40).unwrap();
+        stills.load(&paths[1], 40).unwrap();
+        // Showing `a` again makes `b` the least recently shown.
+        stills.load(&paths[0], 40).unwrap();
+        assert_eq!(stills.used, 32);
+        stills.load(&paths[2], 40).unwrap();
+        assert_eq!(loaded(&stills), ["a", "c"]);
+        assert_eq!(stills.used, 32);
+    }
+
+    #[test]
+    fn the_shown_image_is_kept_even_if_it_is_over_the_budget() {
+        let (_directory, paths) = images(&["a", "b"]);
+        let mut stills = Stills::default();
+        stills.load(&paths[0], 8).unwrap();
+        assert_eq!(loaded(&stills), ["a"]);
+        stills.load(&paths[1], 8).unwrap();
+        assert_eq!(loaded(&stills), ["b"]);
+        assert_eq!(stills.used, 16);
+    }
+
+    #[test]
+    fn images_within_the_budget_are_all_kept() {
+        let (_directory, paths) = images(&["a", "b", "c"]);
+        let mut stills = Stills::default();
+        for path in &paths {
+            stills.load(path, 48).unwrap();
+        }
+        assert_eq!(loaded(&stills), ["a", "b", "c"]);
+        assert_eq!(stills.used, 48);
+    }
+
+    #[test]
+    fn images_that_fail_to_decode_are_remembered() {
+        let (_directory, paths) = images(&["broken"]);
+        fs::write(&paths[0], b"not an image").unwrap();
+        let mut stills = Stills::default();
+        let error = stills.load(&paths[0], 48).unwrap_err();
+        // The file isn't read again, so fixing it needs the config to be reloaded.
+        RgbaImage::new(2, 2).save(&paths[0]).unwrap();
+        assert_eq!(stills.load(&paths[0], 48), Err(error));
+        assert_eq!(stills.used, 0);
+    }
+
+    fn raster_size(rasters: &Rasters, path: &Path, scale: f32) -> (u32, u32) {
+        match rasters.get(path, scale).unwrap().handle {
+            image::Handle::Rgba { width, height, .. } => (width, height),
+            handle => panic!("{handle:?} should be raw pixels"),
+        }
+    }
+
+    #[test]
+    fn the_old_raster_is_drawn_until_the_new_one_is_ready() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("avatar.svg");
+        fs::write(
+            &path,
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">"#,
+                r#"<rect width="4" height="2"/></svg>"#,
+            ),
+        )
+        .unwrap();
+        let (sender, receiver) = async_channel::unbounded();
+        let rasters = Rasters::new(sender);
+        rasters.load(&path);
+        assert_eq!(raster_size(&rasters, &path, 1.0), (4, 2));
+        assert_eq!(raster_size(&rasters, &path, 2.0), (4, 2));
+        assert!(matches!(
+            receiver.recv_blocking(),
+            Ok(Message::CurrentImageChanged)
+        ));
+        assert_eq!(raster_size(&rasters, &path, 2.0), (8, 4));
+    }
+}
@@ -1,26 +1,42 @@
-use std::{env, time::Duration};
+use std::sync::{Arc, Mutex};
 
 use app::App;
-use iced::Task;
-use rotatar_backend::{Message, audio::AudioHandler};
-use rotatar_types::{Config, FrontendError, TwoInts, ValidArgs};
+use async_channel::Sender;
+use cache::ImageCache;
+use iced::{Size, window};
+use rotatar_backend::{Animations, Frontend, Message, State, audio::AudioHandler};
+use rotatar_types::{Config, FrontendError, ValidArgs};
 use util::ToIcedColor;
 
 mod app;
+mod avatar;
+mod cache;
 mod util;
 
-pub async fn run(args: ValidArgs, config: Config) -> Result<(), FrontendError> {
+pub async fn run(
+    args: ValidArgs,
+    config: Config,
+    animations: Animations,
+) -> Result<(), FrontendError> {
     let (sender, receiver) = async_channel::unbounded();
     let cloned_sender = sender.clone();
     let (audio_sender, audio_receiver) = async_channel::bounded(5);
-    let background_color = if let Some(background_color) = args.background_color() {
+    // A chroma key preset takes the place of the background color, as it is meant to be keyed out.
+    let background_color = if let Some(chroma_key) = config.effects().chroma_key() {
+        chroma_key.color().into_iced()
+    } else if let Some(background_color) = args.background_color() {
         background_color.into_iced()
     } else {
         iced::Color::TRANSPARENT
     };
     let audio_config = config.audio();
+    let window_size = config.window().size();
+    let animations = Arc::new(animations);
+    let budget = args.image_cache_size() * 1024 * 1024;
+    let images = ImageCache::new(&config, &animations, budget, sender.clone());
     let app = App::new(
         config,
+        images,
         background_color,
         receiver,
         sender.clone(),
@@ -28,32 +44,22 @@ pub async fn run(args: ValidArgs, config: Config) -> Result<(), FrontendError> {
         audio_receiver.clone(),
     );
 
-    let state = app.state();
-    let managed_config = app.config();
-    tokio::spawn(async move {
-        let modifiers = if let Ok(config) = managed_config.lock() {
-            config.screen_information().modifier(env::consts::OS)
-        } else {
-            TwoInts::new(0, 0)
-        };
-        let receiver = rotatar_backend::get_mouse_pos(Duration::from_millis(100), modifiers).await;
-        loop {
-            if let Ok(position) = receiver.recv().await {
-                let mut message_to_send = None;
-                match state.lock() {
-                    Ok(mut state) => {
-                        if state.set_current_image_xy(position.x(), position.y()) {
-                            message_to_send = Some(Message::CurrentImageChanged);
-                        }
-                    }
-                    Err(error) => todo!("{error}"),
-                }
-                if let Some(message_to_send) = message_to_send {
-                    sender.send(message_to_send).await.unwrap();
-                }
-            }
-        }
-    });
+    if let (Ok(mut state), Ok(config)) = (app.state().lock(), app.config().lock()) {
+        state.restore_props(rotatar_backend::props_path(args.config_path()), &config);
+    }
+
+    tokio::spawn(rotatar_backend::run_loops(
+        Shared {
+            state: app.state(),
+            config: app.config(),
+            images: app.images(),
+            budget,
+            sender: sender.clone(),
+        },
+        args,
+        animations,
+        sender,
+    ));
 
     let _ = cloned_sender
         .send(Message::SetupAudio(AudioHandler::new(
@@ -62,10 +68,19 @@ pub async fn run(args: ValidArgs, config: Config) -> Result<(), FrontendError> {
             audio_config,
         )))
         .await;
-    let result = iced::application("rotatar", App::update, App::view)
+    let mut application = iced::application("rotatar", App::update, App::view)
         .transparent(true)
-        .subscription(App::subscription)
-        .run_with(|| (app, Task::none()));
+        .subscription(App::subscription);
+    if let Some((width, height)) = window_size {
+        application = application.window_size(Size::new(width as f32, height as f32));
+    }
+    let result = application.run_with(|| {
+        // The window isn't always resized when it opens, so its first size is asked for.
+        let window_size = window::get_latest()
+            .and_then(window::get_size)
+            .map(|size| Message::WindowResized(size.width, size.height));
+        (app, window_size)
+    });
 
     if result.is_err() {
         Err(FrontendError::Iced)
@@ -73,3 +88,36 @@ pub async fn run(args: ValidArgs, config: Config) -> Result<(), FrontendError> {
         Ok(())
     }
 }
+
+/// Everything the backend's loops share with the app.
+#[derive(Clone)]
+struct Shared {
+    state: Arc<Mutex<State>>,
+    config: Arc<Mutex<Config>>,
+    images: Arc<Mutex<Arc<ImageCache>>>,
+    /// The most memory, in bytes, that decoded still images may use.
+    budget: usize,
+    sender: Sender<Message>,
+}
+
+impl Frontend for Shared {
+    type Images = ImageCache;
+
+    fn state(&self) -> &Mutex<State> {
+        &self.state
+    }
+
+    fn config(&self) -> &Mutex<Config> {
+        &self.config
+    }
+
+    fn load_images(&self, config: &Config, animations: Arc<Animations>) -> ImageCache {
+        ImageCache::new(config, &animations, self.budget, self.sender.clone())
+    }
+
+    fn swap_images(&self, images: ImageCache) {
+        if let Ok(mut current) = self.images.lock() {
+            *current = Arc::new(images);
+        }
+    }
+}
@@ -10,7 +10,7 @@ impl ToIcedColor for Color {
             r: self.r() as f32 / 255.0,
             g: self.g() as f32 / 255.0,
             b: self.b() as f32 / 255.0,
-            a: 1.0,
+            a: self.a() as f32 / 255.0,
         }
     }
 }
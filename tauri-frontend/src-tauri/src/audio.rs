@@ -5,7 +5,6 @@ use rotatar_backend::{
     Message, State,
     audio::{AudioHandler, AudioMessage},
 };
-use rotatar_types::Config;
 use tauri::{AppHandle, Manager};
 
 #[tauri::command(async)]
@@ -24,7 +23,7 @@ pub async fn set_audio_device(device: String, app_handle: AppHandle) {
     let mut audio_handler = AudioHandler::new(
         message_sender.clone(),
         audio_receiver,
-        app_handle.state::<Config>().audio(),
+        crate::current_config(&app_handle).audio(),
     );
     audio_handler.update_input_devices();
     audio_handler.set_input_device_from_name(device).unwrap();
@@ -40,7 +39,7 @@ pub fn set_up_audio(
         AudioHandler::new(
             sender.clone(),
             receiver,
-            app_handle.state::<Config>().audio(),
+            crate::current_config(&app_handle).audio(),
         ),
         sender,
     );
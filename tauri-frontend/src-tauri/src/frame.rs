@@ -0,0 +1,113 @@
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+
+use percent_encoding::percent_decode_str;
+use rotatar_backend::svg;
+use rotatar_types::{ImageSource, SheetCell};
+use tauri::{
+    AppHandle, Manager,
+    http::{Request, Response, StatusCode, header::CONTENT_TYPE},
+};
+
+/// Serves single frames of animated images through the `frame` protocol, so the page can show
+/// exactly the frame the backend picked. Requests look like `frame://localhost/<path>?frame=<n>`,
+/// which is what `convertFileSrc(path, "frame")` creates with the query added on. Sprite sheet
+/// cells add `&cell=<x>,<y>,<width>,<height>` to pick the cell out of the sheet at `<path>`.
+///
+/// SVG images are rasterized instead, at the size asked for with `?size=<width>,<height>` in
+/// physical pixels. Sizes larger than [`svg::MAX_SIZE`] are refused.
+pub fn serve_frame(app_handle: &AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let path = PathBuf::from(
+        percent_decode_str(request.uri().path().trim_start_matches('/'))
+            .decode_utf8_lossy()
+            .into_owned(),
+    );
+    let query = |key: &str| {
+        request.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+        })
+    };
+    let frame = query("frame")
+        .and_then(|frame| frame.parse::<usize>().ok())
+        .unwrap_or_default();
+    let size = query("size").and_then(|size| {
+        let (width, height) = size.split_once(',')?;
+        Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?))
+    });
+    if let (true, Some(size)) = (svg::is_svg(&path), size) {
+        return png_response(rasterize(app_handle, path, size).map(Some));
+    }
+    let cell = query("cell").and_then(|cell| {
+        let cell = cell
+            .split(',')
+            .map(|value| value.parse::<u32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        match cell[..] {
+            [x, y, width, height] => Some((x, y, width, height)),
+            _ => None,
+        }
+    });
+    let source = match cell {
+        Some((x, y, width, height)) => ImageSource::Cell(SheetCell::new(path, x, y, width, height)),
+        None => ImageSource::Path(path),
+    };
+
+    let animations = crate::current_animations(app_handle);
+    let png = animations
+        .get(&source)
+        .and_then(|animation| animation.frame(frame))
+        .and_then(|frame| frame.image().png())
+        .transpose()
+        .map(|png| png.map(<[u8]>::to_vec))
+        .map_err(|error| error.to_string());
+    png_response(png)
+}
+
+/// The last rasterization of each SVG image, so that the page can ask for an image again without
+/// it being rasterized again until the window is resized.
+#[derive(Default)]
+pub struct SvgRasters(Mutex<HashMap<PathBuf, ((u32, u32), Vec<u8>)>>);
+
+impl SvgRasters {
+    /// Forgets every rasterization, for when the images may have changed.
+    pub fn clear(&self) {
+        if let Ok(mut rasters) = self.0.lock() {
+            rasters.clear();
+        }
+    }
+}
+
+fn rasterize(app_handle: &AppHandle, path: PathBuf, size: (u32, u32)) -> Result<Vec<u8>, String> {
+    let rasters = app_handle.state::<SvgRasters>();
+    let mut rasters = rasters
+        .0
+        .lock()
+        .map_err(|_| String::from("The SVG rasters were poisoned"))?;
+    if let Some((_, png)) = rasters.get(&path).filter(|(rastered, _)| *rastered == size) {
+        return Ok(png.clone());
+    }
+    let png = svg::rasterize_png(&path, size)?;
+    rasters.insert(path, (size, png.clone()));
+    Ok(png)
+}
+
+fn png_response(png: Result<Option<Vec<u8>>, String>) -> Response<Vec<u8>> {
+    match png {
+        Ok(Some(png)) => Response::builder()
+            .header(CONTENT_TYPE, "image/png")
+            .body(png),
+        Err(error) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(error.into_bytes()),
+        Ok(None) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Vec::new()),
+    }
+    .unwrap_or_else(|_| {
+        panic!(
+            "Frame response should always be valid. Found in: {}",
+            file!()
+        )
+    })
+}
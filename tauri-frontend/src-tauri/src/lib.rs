@@ -1,63 +1,79 @@
-use std::{env, sync::Mutex, time::Duration};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
 
 use async_channel::Sender;
 use audio::set_up_audio;
-use rotatar_backend::{Message, State, audio::AudioStatus, get_mouse_pos, set_state};
-use rotatar_types::Config;
-use tauri::{AppHandle, Emitter, Manager, generate_context};
+use rotatar_backend::{
+    Animations, DisplayedFrame, Frontend, Message, State, audio::AudioStatus, props_path,
+    run_loops, save_props, set_state, svg,
+};
+use rotatar_types::{Config, ImageSource, SheetCell, ValidArgs};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, LogicalSize, Manager, generate_context};
 
 mod audio;
+mod frame;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run(config: Config) {
+pub fn run(args: ValidArgs, config: Config, animations: Animations) {
+    // A chroma key preset takes the place of the background color, as it is meant to be keyed out.
+    let background_color = BackgroundColor(
+        config
+            .effects()
+            .chroma_key()
+            .map(|chroma_key| chroma_key.color())
+            .or(args.background_color())
+            .map(|color| color.to_css()),
+    );
     let (sender, receiver) = async_channel::unbounded();
+    let animations = Arc::new(animations);
     let (audio_sender, audio_receiver) = async_channel::bounded(5);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .register_uri_scheme_protocol("frame", |context, request| {
+            frame::serve_frame(context.app_handle(), request)
+        })
         .invoke_handler(tauri::generate_handler![
             get_config,
+            get_background_color,
             get_state,
+            get_displayed_image,
+            get_displayed_layers,
+            get_displayed_props,
+            get_displayed_blend,
+            set_expression,
+            set_prop,
             audio::set_audio_device
         ])
         .setup(move |app| {
-            app.manage(Mutex::new(State::new(
+            if let (Some((width, height)), Some(window)) =
+                (config.window().size(), app.get_webview_window("main"))
+            {
+                let _ = window.set_size(LogicalSize::new(width, height));
+            }
+            let mut state = State::new(
                 sender.clone(),
                 config.screen_information().size(),
                 config.sections(),
                 audio_sender,
                 audio_receiver.clone(),
-            )));
-            app.manage(config);
+            );
+            state.restore_props(props_path(args.config_path()), &config);
+            app.manage(Mutex::new(state));
+            app.manage(Mutex::new(config.clone()));
+            app.manage(background_color);
+            app.manage(Mutex::new(animations.clone()));
+            app.manage(frame::SvgRasters::default());
 
-            let mouse_app_handle = app.handle().clone();
-            let mouse_sender = sender.clone();
-            tauri::async_runtime::spawn(async move {
-                let mouse_pos_receiver = get_mouse_pos(
-                    Duration::from_millis(100),
-                    mouse_app_handle
-                        .state::<Config>()
-                        .screen_information()
-                        .modifier(env::consts::OS),
-                )
-                .await;
-                loop {
-                    if let Ok(position) = mouse_pos_receiver.recv().await {
-                        let mut message_to_send = None;
-                        match mouse_app_handle.state::<Mutex<State>>().lock() {
-                            Ok(mut state) => {
-                                if state.set_current_image_xy(position.x(), position.y()) {
-                                    message_to_send = Some(Message::CurrentImageChanged);
-                                }
-                            }
-                            Err(error) => todo!("{error}"),
-                        }
-                        if let Some(message_to_send) = message_to_send {
-                            mouse_sender.send(message_to_send).await.unwrap();
-                        }
-                    }
-                }
-            });
+            tauri::async_runtime::spawn(run_loops(
+                Shared(app.handle().clone()),
+                args.clone(),
+                animations.clone(),
+                sender.clone(),
+            ));
 
             set_up_audio(sender.clone(), audio_receiver, app.handle().clone());
 
@@ -85,7 +101,7 @@ async fn handle_message(sender: Sender<Message>, app_handle: AppHandle, message:
     match message {
         Message::SetupAudio(mut audio_handler) => {
             audio_handler.update_input_devices();
-            let audio_device = app_handle.state::<Config>().audio().current_device();
+            let audio_device = current_config(&app_handle).audio().current_device();
             tauri::async_runtime::spawn(async move {
                 sender
                     .send(rotatar_backend::audio::handle_audio(audio_handler, audio_device).await)
@@ -96,7 +112,7 @@ async fn handle_message(sender: Sender<Message>, app_handle: AppHandle, message:
         Message::UpdateAudioStatus(audio_status, audio_handler) => {
             if audio_status == AudioStatus::Polling {
                 if let Some(audio_handler) = audio_handler {
-                    let audio_device = app_handle.state::<Config>().audio().current_device();
+                    let audio_device = current_config(&app_handle).audio().current_device();
                     tauri::async_runtime::spawn(async move {
                         rotatar_backend::audio::wait_for_audio(audio_handler, audio_device).await;
                     });
@@ -111,6 +127,18 @@ async fn handle_message(sender: Sender<Message>, app_handle: AppHandle, message:
         }
         Message::SensitivityChanged(sensitivity) => {
             app_handle.emit("sensitivity-changed", sensitivity).unwrap();
+            let speaking_changed =
+                app_handle
+                    .state::<Mutex<State>>()
+                    .lock()
+                    .is_ok_and(|mut state| {
+                        let was_speaking = state.is_speaking();
+                        state.set_sensitivity(sensitivity);
+                        was_speaking != state.is_speaking()
+                    });
+            if speaking_changed {
+                emit_displayed_image(&app_handle);
+            }
         }
         Message::CurrentImageChanged => {
             let _ = app_handle.emit(
@@ -121,12 +149,100 @@ async fn handle_message(sender: Sender<Message>, app_handle: AppHandle, message:
                     .unwrap_or_else(|_| panic!("State mutex was poisoned in {}", file!()))
                     .current_image(),
             );
+            emit_displayed_image(&app_handle);
         }
         Message::MagnitudeChanged(magnitude) => {
             app_handle.emit("magnitude-changed", magnitude).unwrap();
+            set_state!(app_handle.state::<Mutex<State>>(), set_magnitude, magnitude);
         }
         Message::ConfigChanged(config) => {
             app_handle.emit("config-changed", config).unwrap();
+            emit_displayed_image(&app_handle);
+        }
+        Message::TypingChanged(activity) => {
+            app_handle.emit("typing-changed", activity).unwrap();
+            emit_displayed_image(&app_handle);
+        }
+        Message::SleepingChanged(sleeping) => {
+            app_handle.emit("sleeping-changed", sleeping).unwrap();
+            emit_displayed_image(&app_handle);
+        }
+        Message::ReactionChanged(reaction) => {
+            app_handle.emit("reaction-changed", reaction).unwrap();
+            emit_displayed_image(&app_handle);
+        }
+        Message::IdleActionChanged(idle_action) => {
+            app_handle.emit("idle-action-changed", idle_action).unwrap();
+            emit_displayed_image(&app_handle);
+        }
+        Message::BlinkingChanged(blinking) => {
+            app_handle.emit("blinking-changed", blinking).unwrap();
+            emit_displayed_image(&app_handle);
+        }
+        Message::TransformChanged(transform) => {
+            app_handle.emit("transform-changed", transform).unwrap();
+        }
+        Message::SpringsChanged(_) => {
+            // Only the layers and props move with their springs, so the rest is not sent again.
+            let _ = app_handle.emit(
+                "displayed-layers-changed",
+                get_displayed_layers(app_handle.clone()),
+            );
+            let _ = app_handle.emit(
+                "displayed-props-changed",
+                get_displayed_props(app_handle.clone()),
+            );
+        }
+        Message::TransitionChanged(transition) => {
+            let animations = current_animations(&app_handle);
+            let transition = transition.and_then(|transition| {
+                Some(DisplayedTransition {
+                    previous: displayed_image(&animations, transition.previous(), 0)?,
+                    progress: transition.progress(),
+                })
+            });
+            app_handle.emit("transition-changed", transition).unwrap();
+        }
+        Message::SetExpression(expression) => {
+            if let Some(name) = expression
+                .as_ref()
+                .filter(|name| current_config(&app_handle).expression(name).is_none())
+            {
+                log::warn!("There is no expression called '{name}'");
+                return;
+            }
+            let changed = app_handle
+                .state::<Mutex<State>>()
+                .lock()
+                .is_ok_and(|mut state| state.set_expression(expression.clone()));
+            if changed {
+                app_handle.emit("expression-changed", expression).unwrap();
+                emit_displayed_image(&app_handle);
+            }
+        }
+        Message::SetProp(name, shown) => {
+            if current_config(&app_handle).prop(&name).is_none() {
+                log::warn!("There is no prop called '{name}'");
+                return;
+            }
+            let changed = app_handle
+                .state::<Mutex<State>>()
+                .lock()
+                .ok()
+                .and_then(|mut state| {
+                    let changed = state.set_prop(name, shown);
+                    changed.then(|| (state.props().clone(), state.saved_props()))
+                });
+            if let Some((props, saved)) = changed {
+                if let Some((path, shown)) = saved {
+                    save_props(&path, &shown);
+                }
+                app_handle.emit("props-changed", props).unwrap();
+                emit_displayed_image(&app_handle);
+            }
+        }
+        Message::FramesChanged(_) => {
+            emit_displayed_image(&app_handle);
         }
         Message::AudioDevicesChanged(devices) => {
             app_handle.emit("audio-devices-changed", &devices).unwrap();
@@ -140,9 +256,151 @@ async fn handle_message(sender: Sender<Message>, app_handle: AppHandle, message:
     }
 }
 
+/// The image that should currently be shown, with the frame to show if the image is animated.
+#[derive(Clone, Serialize)]
+struct DisplayedImage {
+    path: PathBuf,
+    frame: Option<usize>,
+    /// The part of the image to show, if it is a sprite sheet.
+    cell: Option<SheetCell>,
+    /// The size of the image before it is scaled to the window.
+    size: Option<(u32, u32)>,
+    /// SVG images have to be rasterized at the size they are shown at.
+    svg: bool,
+}
+
+/// An image blended with others, with the opacity it is drawn with when stacked in order.
+#[derive(Clone, Serialize)]
+struct BlendedImage {
+    image: DisplayedImage,
+    opacity: f32,
+}
+
+/// A layer or prop drawn over the avatar, moved from where it would be by the offset.
+#[derive(Clone, Serialize)]
+struct PlacedImage {
+    image: DisplayedImage,
+    offset: (f32, f32),
+}
+
+/// The image being transitioned away from.
+#[derive(Clone, Serialize)]
+struct DisplayedTransition {
+    previous: DisplayedImage,
+    progress: f32,
+}
+
+/// Emits the image, layers and props that should currently be shown, so the page never has to work out
+/// which images belong to the current state itself.
+fn emit_displayed_image(app_handle: &AppHandle) {
+    let _ = app_handle.emit(
+        "displayed-image-changed",
+        get_displayed_image(app_handle.clone()),
+    );
+    let _ = app_handle.emit(
+        "displayed-blend-changed",
+        get_displayed_blend(app_handle.clone()),
+    );
+    let _ = app_handle.emit(
+        "displayed-layers-changed",
+        get_displayed_layers(app_handle.clone()),
+    );
+    let _ = app_handle.emit(
+        "displayed-props-changed",
+        get_displayed_props(app_handle.clone()),
+    );
+}
+
+fn displayed_image(
+    animations: &Animations,
+    source: &ImageSource,
+    frame: usize,
+) -> Option<DisplayedImage> {
+    let size = animations.size(source);
+    match animations.display(source, frame)? {
+        DisplayedFrame::File(path) => Some(DisplayedImage {
+            path: path.clone(),
+            frame: None,
+            cell: None,
+            size,
+            svg: svg::is_svg(path),
+        }),
+        DisplayedFrame::Decoded(path, frame, _) => Some(DisplayedImage {
+            path: path.to_path_buf(),
+            frame: Some(frame),
+            cell: None,
+            size,
+            svg: false,
+        }),
+        DisplayedFrame::Cell(cell, _) => Some(DisplayedImage {
+            path: cell.sheet().clone(),
+            frame: Some(0),
+            cell: Some(cell.clone()),
+            size,
+            svg: false,
+        }),
+    }
+}
+
+/// Locks the config, which is replaced whenever the config file changes.
+fn current_config(app_handle: &AppHandle) -> MutexGuard<'_, Config> {
+    app_handle
+        .state::<Mutex<Config>>()
+        .inner()
+        .lock()
+        .unwrap_or_else(|_| panic!("The config mutex was poisoned. Found in: {}", file!()))
+}
+
+/// Gets the animations of the current config.
+fn current_animations(app_handle: &AppHandle) -> Arc<Animations> {
+    app_handle
+        .state::<Mutex<Arc<Animations>>>()
+        .lock()
+        .unwrap_or_else(|_| panic!("The animations mutex was poisoned. Found in: {}", file!()))
+        .clone()
+}
+
+/// Gives the backend's loops the state and config managed by the app.
+#[derive(Clone)]
+struct Shared(AppHandle);
+
+impl Frontend for Shared {
+    type Images = Arc<Animations>;
+
+    fn state(&self) -> &Mutex<State> {
+        self.0.state::<Mutex<State>>().inner()
+    }
+
+    fn config(&self) -> &Mutex<Config> {
+        self.0.state::<Mutex<Config>>().inner()
+    }
+
+    /// Frames are served straight from the animations, so only the SVG rasterizations are thrown
+    /// away, as any of the images may have changed. SVG images are rasterized from the disk, so
+    /// this can happen before the new config is swapped in.
+    fn load_images(&self, _config: &Config, animations: Arc<Animations>) -> Arc<Animations> {
+        self.0.state::<frame::SvgRasters>().clear();
+        animations
+    }
+
+    fn swap_images(&self, animations: Arc<Animations>) {
+        if let Ok(mut current) = self.0.state::<Mutex<Arc<Animations>>>().lock() {
+            *current = animations;
+        }
+    }
+}
+
 #[tauri::command]
 fn get_config(app_handle: AppHandle) -> Config {
-    app_handle.state::<Config>().inner().clone()
+    current_config(&app_handle).clone()
+}
+
+/// The chroma key color, or the background color passed on the command line, as a CSS color.
+struct BackgroundColor(Option<String>);
+
+#[tauri::command]
+fn get_background_color(app_handle: AppHandle) -> Option<String> {
+    app_handle.state::<BackgroundColor>().0.clone()
 }
 
 #[tauri::command]
@@ -153,3 +411,115 @@ fn get_state(app_handle: AppHandle) -> State {
         .unwrap_or_else(|_| panic!("The state mutex was poisoned. Found in: {}", file!()))
         .clone()
 }
+
+/// Switches to the named expression, or back to the default images when `expression` is `None`.
+#[tauri::command]
+fn set_expression(app_handle: AppHandle, expression: Option<String>) {
+    // Only the sender is needed, which a panic while the state was locked can't have broken.
+    let sender = app_handle
+        .state::<Mutex<State>>()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .message_sender();
+    let _ = sender.try_send(Message::SetExpression(expression));
+}
+
+/// Shows or hides the named prop.
+#[tauri::command]
+fn set_prop(app_handle: AppHandle, name: String, shown: bool) {
+    let sender = app_handle
+        .state::<Mutex<State>>()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .message_sender();
+    let _ = sender.try_send(Message::SetProp(name, shown));
+}
+
+#[tauri::command]
+fn get_displayed_image(app_handle: AppHandle) -> Option<DisplayedImage> {
+    let state = app_handle
+        .state::<Mutex<State>>()
+        .lock()
+        .unwrap_or_else(|_| panic!("The state mutex was poisoned. Found in: {}", file!()))
+        .clone();
+    let config = current_config(&app_handle);
+    let source = state.current_image_source(&config)?;
+    displayed_image(
+        &current_animations(&app_handle),
+        source,
+        state.frame(source),
+    )
+}
+
+/// Gets the section images to stack in place of the displayed image, when blending sections is
+/// enabled. Each image is given the opacity that makes it end up with its weight once every image
+/// before it has been drawn.
+#[tauri::command]
+fn get_displayed_blend(app_handle: AppHandle) -> Vec<BlendedImage> {
+    let state = app_handle
+        .state::<Mutex<State>>()
+        .lock()
+        .unwrap_or_else(|_| panic!("The state mutex was poisoned. Found in: {}", file!()))
+        .clone();
+    let config = current_config(&app_handle);
+    if !config.transition().blend_sections() {
+        return Vec::new();
+    }
+    let animations = current_animations(&app_handle);
+    let mut total_weight = 0.0;
+    state
+        .current_blend(&config)
+        .into_iter()
+        .filter_map(|(source, weight)| {
+            total_weight += weight;
+            Some(BlendedImage {
+                image: displayed_image(&animations, source, state.frame(source))?,
+                opacity: weight / total_weight,
+            })
+        })
+        .collect()
+}
+
+/// Gets the layers drawn on top of the displayed image, from bottom to top.
+#[tauri::command]
+fn get_displayed_layers(app_handle: AppHandle) -> Vec<PlacedImage> {
+    let state = app_handle
+        .state::<Mutex<State>>()
+        .lock()
+        .unwrap_or_else(|_| panic!("The state mutex was poisoned. Found in: {}", file!()))
+        .clone();
+    let config = current_config(&app_handle);
+    let animations = current_animations(&app_handle);
+    state
+        .current_layer_sources(&config)
+        .into_iter()
+        .filter_map(|(source, offset)| {
+            Some(PlacedImage {
+                image: displayed_image(&animations, source, state.frame(source))?,
+                offset,
+            })
+        })
+        .collect()
+}
+
+/// Gets the props drawn on top of the layers, from bottom to top.
+#[tauri::command]
+fn get_displayed_props(app_handle: AppHandle) -> Vec<PlacedImage> {
+    let state = app_handle
+        .state::<Mutex<State>>()
+        .lock()
+        .unwrap_or_else(|_| panic!("The state mutex was poisoned. Found in: {}", file!()))
+        .clone();
+    let config = current_config(&app_handle);
+    let animations = current_animations(&app_handle);
+    state
+        .current_prop_sources(&config)
+        .into_iter()
+        .filter_map(|(source, offset)| {
+            Some(PlacedImage {
+                image: displayed_image(&animations, source, state.frame(source))?,
+                offset,
+            })
+        })
+        .collect()
+}
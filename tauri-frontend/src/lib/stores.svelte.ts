@@ -1,11 +1,31 @@
-import type { Config, State } from "./types";
+import type {
+  BlendedImage,
+  Config,
+  DisplayedImage,
+  PlacedImage,
+  DisplayedTransition,
+  State,
+} from "./types";
 
 export const frontendData: FrontendData = $state({
   config: undefined,
   state: undefined,
+  displayedImage: undefined,
+  displayedLayers: [],
+  displayedProps: [],
+  displayedBlend: [],
+  displayedTransition: null,
+  revision: 0,
 });
 
 type FrontendData = {
   config?: Config;
-  state?: State
+  state?: State;
+  displayedImage?: DisplayedImage | null;
+  displayedLayers: PlacedImage[];
+  displayedProps: PlacedImage[];
+  displayedBlend: BlendedImage[];
+  displayedTransition: DisplayedTransition | null;
+  /** Counts how often the config was reloaded, so that images are not shown from the cache. */
+  revision: number;
 };
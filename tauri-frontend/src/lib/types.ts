@@ -2,11 +2,176 @@ export type TwoInts = [number, number];
 
 export type Config = {
   audio: AudioConfig;
+  tracking: TrackingConfig;
+  transition: TransitionConfig;
+  window: WindowConfig;
+  effects: EffectsConfig;
   sections: TwoInts;
-  idle_images: string[];
-  speaking_images: string[];
+  idle_images: ImageSource[];
+  speaking_images: ImageSource[];
+  idle_sheet?: SpriteSheet;
+  speaking_sheet?: SpriteSheet;
+  image_directory?: ImageDirectory;
+  layers: LayerConfig[];
+  outside_images?: OutsideImages;
+  typing?: TypingConfig;
+  afk?: AfkConfig;
+  click_reactions?: ClickReactionsConfig;
+  blink?: BlinkConfig;
+  idle_actions?: IdleActionsConfig;
+  expressions: Record<string, ExpressionConfig>;
+  motion?: MotionConfig;
+  props: PropConfig[];
   screen_information: ScreenInformation;
 };
+export type ImageSource = string | FrameSequence | SheetCell;
+export type SheetCell = {
+  sheet: string;
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+};
+export type SpriteSheet = {
+  path: string;
+  cell_size?: TwoInts;
+  grid?: TwoInts;
+  atlas?: string;
+};
+export type FrameSequence = {
+  frames: SequenceFrame[];
+  mode: "loop" | "ping_pong" | "once";
+};
+export type SequenceFrame = {
+  path: string;
+  duration: number;
+};
+export type OutsideImages = {
+  idle: ImageSource;
+  speaking: ImageSource;
+};
+export type TypingConfig = {
+  timeout: number;
+  rate_window: number;
+  idle_images: ImageSource[];
+  speaking_images: ImageSource[];
+  blinking_idle_images: ImageSource[];
+  blinking_speaking_images: ImageSource[];
+};
+export type AfkConfig = {
+  timeout: number;
+  sleeping_images: ImageSource[];
+  frame_duration: number;
+};
+export type ClickReactionsConfig = {
+  left?: ReactionConfig;
+  right?: ReactionConfig;
+};
+export type ReactionConfig = {
+  images: ImageSource[];
+  duration: number;
+};
+export type ImageDirectory = {
+  path: string;
+  idle: string;
+  speaking: string;
+};
+export type FitMode = "native" | "contain" | "cover" | "integer_nearest";
+export type Anchor =
+  | "center"
+  | "top"
+  | "bottom"
+  | "left"
+  | "right"
+  | "top_left"
+  | "top_right"
+  | "bottom_left"
+  | "bottom_right";
+export type WindowConfig = {
+  size?: TwoInts;
+  scale: number;
+  fit: FitMode;
+  anchor: Anchor;
+  padding: number;
+};
+export type ChromaKey = "green" | "blue" | "magenta";
+export type OutlineConfig = {
+  color: string;
+  width: number;
+};
+export type ShadowConfig = {
+  color: string;
+  offset: [number, number];
+  blur: number;
+};
+export type EffectsConfig = {
+  chroma_key?: ChromaKey;
+  outline?: OutlineConfig;
+  shadow?: ShadowConfig;
+};
+export type TransitionConfig = {
+  kind: "cut" | "crossfade" | "slide";
+  duration: number;
+  blend_sections: boolean;
+};
+export type ExpressionConfig = {
+  hotkey?: string;
+  idle_images: ImageSource[];
+  speaking_images: ImageSource[];
+  layers: Record<string, ImageSource[]>;
+  timeout?: number;
+};
+export type PropConfig = {
+  name: string;
+  image: ImageSource;
+  offsets: [number, number][];
+  shown: boolean;
+  hotkey?: string;
+  spring?: SpringConfig;
+};
+export type MotionConfig = {
+  idle: Transform;
+  speaking: Transform;
+  easing: "linear" | "ease_in" | "ease_out" | "ease_in_out";
+  driver: "sensitivity" | "magnitude";
+  smoothing: number;
+};
+export type Transform = {
+  offset: number;
+  scale_x: number;
+  scale_y: number;
+  rotation: number;
+};
+export type LayerConfig = {
+  name: string;
+  images: ImageSource[];
+  speaking_images: ImageSource[];
+  blinking_images: ImageSource[];
+  spring?: SpringConfig;
+};
+export type BlinkConfig = {
+  idle_images: ImageSource[];
+  speaking_images: ImageSource[];
+  min_interval: number;
+  max_interval: number;
+  duration: number;
+};
+export type IdleAction = {
+  name: string;
+  images: ImageSource[];
+  weight: number;
+  duration: number;
+};
+export type IdleActionsConfig = {
+  actions: IdleAction[];
+  min_interval: number;
+  max_interval: number;
+  quiet_time: number;
+};
+export type TrackingConfig = {
+  mode: "poll" | "event";
+  poll_interval: number;
+};
 export type AudioConfig = {
   current_device: number;
   magnitude_threshold: number;
@@ -24,6 +189,65 @@ export type State = {
   // this will stay accepting any.
   audio_status: "Ready" | "Closed" | any;
   audio_devices: string[];
+  screen_size: TwoInts;
   section_size: TwoInts;
   x_sections: number;
+  y_sections: number;
+  cursor_outside: boolean;
+  cursor_position: TwoInts;
+  typing: TypingActivity;
+  sleeping: number | null;
+  reaction: ClickButton | null;
+  idle_action: number | null;
+  magnitude: number;
+  blinking: boolean;
+  transform: Transform;
+  section_position: [number, number];
+  transition: Transition | null;
+  expression: string | null;
+  props: string[];
+  spring_offsets: SpringOffsets;
+};
+export type Transition = {
+  previous: ImageSource;
+  progress: number;
+};
+export type BlendedImage = {
+  image: DisplayedImage;
+  opacity: number;
+};
+// A layer or prop, with how far it is moved from where it would be in pixels of the avatar's
+// images. Props are centered on the avatar before they are moved.
+export type PlacedImage = {
+  image: DisplayedImage;
+  offset: [number, number];
+};
+export type SpringConfig = {
+  stiffness: number;
+  damping: number;
+  section_shift: [number, number];
+  max_offset: number;
+};
+export type SpringOffsets = {
+  layers: [number, number][];
+  props: [number, number][];
+};
+export type DisplayedTransition = {
+  previous: DisplayedImage;
+  progress: number;
+};
+export type ClickButton = "Left" | "Right";
+export type TypingActivity = {
+  active: boolean;
+  key_rate: number;
+};
+
+export type DisplayedImage = {
+  path: string;
+  // Only set when the image is animated or a sprite sheet cell.
+  frame: number | null;
+  cell: SheetCell | null;
+  size: [number, number] | null;
+  // SVG images are rasterized at the size they are shown at.
+  svg: boolean;
 };
@@ -0,0 +1,57 @@
+import type { Anchor, EffectsConfig, WindowConfig } from "$lib/types";
+
+// Works out how much an avatar of `size` is scaled by to be shown in an `area` of the window, the
+// same way the backend's `WindowConfig::scale_for` does.
+export function scaleFor(
+  window: WindowConfig,
+  [width, height]: [number, number],
+  [areaWidth, areaHeight]: [number, number],
+): number {
+  if (width <= 0 || height <= 0) {
+    return window.scale;
+  }
+  const horizontal = Math.max(areaWidth - window.padding * 2, 0) / width;
+  const vertical = Math.max(areaHeight - window.padding * 2, 0) / height;
+  switch (window.fit) {
+    case "native":
+      return window.scale;
+    case "contain":
+      return Math.min(horizontal, vertical) * window.scale;
+    case "cover":
+      return Math.max(horizontal, vertical) * window.scale;
+    case "integer_nearest":
+      return Math.max(Math.floor(Math.min(horizontal, vertical) * window.scale), 1);
+  }
+}
+
+// The flexbox alignment that places the avatar at the anchor, horizontally and then vertically.
+export function anchorAlignment(anchor: Anchor): [string, string] {
+  const position = (edge: "start" | "end" | null) =>
+    edge === "start" ? "flex-start" : edge === "end" ? "flex-end" : "center";
+  const horizontal = anchor.endsWith("left") ? "start" : anchor.endsWith("right") ? "end" : null;
+  const vertical = anchor.startsWith("top") ? "start" : anchor.startsWith("bottom") ? "end" : null;
+  return [position(horizontal), position(vertical)];
+}
+
+// The id of the SVG filter that draws the outline, which the avatar's page defines.
+export const OUTLINE_FILTER_ID = "avatar-outline";
+
+// How far the outline reaches past the edges of the avatar at `scale`, in pixels.
+export function outlineRadius(effects: EffectsConfig, scale: number): number {
+  return effects.outline ? Math.max(effects.outline.width, 0) * scale : 0;
+}
+
+// The CSS filter that draws the outline and shadow around the avatar at `scale`. The outline is
+// drawn once from the grown alpha of the avatar by the SVG filter, and the shadow is cast by the
+// outlined avatar.
+export function effectsFilter(effects: EffectsConfig, scale: number): string {
+  const filters = [];
+  if (outlineRadius(effects, scale) > 0) {
+    filters.push(`url(#${OUTLINE_FILTER_ID})`);
+  }
+  if (effects.shadow) {
+    const [x, y] = effects.shadow.offset;
+    filters.push(`drop-shadow(${x * scale}px ${y * scale}px ${effects.shadow.blur * scale}px ${effects.shadow.color})`);
+  }
+  return filters.join(" ");
+}
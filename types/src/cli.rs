@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use clap::Parser;
 use thiserror::Error;
 
-use crate::{color::Color, frontend::Frontend};
+use crate::{Anchor, ChromaKey, ConfigFormat, FitMode, color::Color, frontend::Frontend};
 
 #[derive(Debug, Error)]
 pub enum ArgsError {
@@ -11,6 +11,8 @@ pub enum ArgsError {
     NoConfig,
     #[error("An invalid frontend was specified because `{0}` is not a frontend")]
     InvalidFrontend(String),
+    #[error("`{0}` is not a valid {1}")]
+    InvalidValue(String, &'static str),
     #[error("Some part of the provided args was invalid and could not be fixed")]
     Invalid,
 }
@@ -19,16 +21,51 @@ pub enum ArgsError {
 pub struct Args {
     #[arg(short = 'c', long = "config")]
     config_path: Option<PathBuf>,
+    /// The format of the config file: json, toml, yaml or ron. By default, this is worked out
+    /// from the extension of the config file, and JSON is used for unknown extensions.
+    #[arg(long = "config-format")]
+    config_format: Option<ConfigFormat>,
     #[arg(short = 'f', long = "frontend")]
     frontend: Option<Frontend>,
+    /// The background color, like `#00ff00`, `#00000080`, `rgba(0, 0, 0, 0.5)` or `green`.
     #[arg(long = "background")]
     background_color: Option<Color>,
+    /// The most memory, in MiB, that the iced frontend uses to keep still images decoded.
+    #[arg(long = "image-cache-size", default_value_t = 512)]
+    image_cache_size: usize,
+    /// The width and height the window opens with, like `400x600`.
+    #[arg(long = "window-size", value_parser = parse_window_size)]
+    window_size: Option<(u32, u32)>,
+    /// Scales the avatar on top of the fit mode, like `2` for twice the size.
+    #[arg(long = "scale")]
+    scale: Option<f32>,
+    /// How the avatar is scaled to the window: native, contain, cover or integer-nearest.
+    #[arg(long = "fit")]
+    fit: Option<FitMode>,
+    /// Where the avatar is placed in the window, like center, top or bottom-left.
+    #[arg(long = "anchor")]
+    anchor: Option<Anchor>,
+    /// Space kept free between the avatar and the edges of the window, in logical pixels.
+    #[arg(long = "padding")]
+    padding: Option<f32>,
+    /// Replaces the background with a color for chroma keying: green, blue or magenta.
+    #[arg(long = "chroma-key")]
+    chroma_key: Option<ChromaKey>,
 }
 
+#[derive(Clone)]
 pub struct ValidArgs {
     config_path: PathBuf,
+    config_format: ConfigFormat,
     frontend: Frontend,
     background_color: Option<Color>,
+    image_cache_size: usize,
+    window_size: Option<(u32, u32)>,
+    scale: Option<f32>,
+    fit: Option<FitMode>,
+    anchor: Option<Anchor>,
+    padding: Option<f32>,
+    chroma_key: Option<ChromaKey>,
 }
 
 impl ValidArgs {
@@ -36,6 +73,10 @@ impl ValidArgs {
         &self.config_path
     }
 
+    pub fn config_format(&self) -> ConfigFormat {
+        self.config_format
+    }
+
     pub fn frontend(&self) -> Frontend {
         self.frontend
     }
@@ -43,6 +84,34 @@ impl ValidArgs {
     pub fn background_color(&self) -> Option<Color> {
         self.background_color
     }
+
+    pub fn image_cache_size(&self) -> usize {
+        self.image_cache_size
+    }
+
+    pub fn window_size(&self) -> Option<(u32, u32)> {
+        self.window_size
+    }
+
+    pub fn scale(&self) -> Option<f32> {
+        self.scale
+    }
+
+    pub fn fit(&self) -> Option<FitMode> {
+        self.fit
+    }
+
+    pub fn anchor(&self) -> Option<Anchor> {
+        self.anchor
+    }
+
+    pub fn padding(&self) -> Option<f32> {
+        self.padding
+    }
+
+    pub fn chroma_key(&self) -> Option<ChromaKey> {
+        self.chroma_key
+    }
 }
 
 impl TryFrom<Args> for ValidArgs {
@@ -50,13 +119,31 @@ impl TryFrom<Args> for ValidArgs {
 
     fn try_from(value: Args) -> Result<Self, Self::Error> {
         if let Some(config_path) = value.config_path {
+            let config_format = value
+                .config_format
+                .or_else(|| ConfigFormat::from_extension(&config_path))
+                .unwrap_or_default();
             Ok(ValidArgs {
                 config_path,
+                config_format,
                 frontend: value.frontend.unwrap_or_default(),
                 background_color: value.background_color,
+                image_cache_size: value.image_cache_size,
+                window_size: value.window_size,
+                scale: value.scale,
+                fit: value.fit,
+                anchor: value.anchor,
+                padding: value.padding,
+                chroma_key: value.chroma_key,
             })
         } else {
             Err(ArgsError::Invalid)
         }
     }
 }
+
+fn parse_window_size(size: &str) -> Result<(u32, u32), ArgsError> {
+    size.split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .ok_or_else(|| ArgsError::InvalidValue(String::from(size), "window size"))
+}
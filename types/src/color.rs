@@ -1,22 +1,182 @@
 use std::{num::ParseIntError, str::FromStr};
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ColorParseError {
-    #[error("Invalid format for color, should be \"r b g\"")]
+    #[error(
+        "Invalid format for color, should be \"r g b\", \"#rrggbb\", \"#rrggbbaa\", \"rgb(r, g, b)\", \"rgba(r, g, b, a)\" or a CSS color name"
+    )]
     InvalidFormat,
     #[error("The provided number was incorrect for a color component")]
     InvalidNumber(#[from] ParseIntError),
+    #[error("The alpha of a color must be a number from 0 to 1, or a percentage")]
+    InvalidAlpha,
+    #[error("`{0}` is not a CSS color name")]
+    UnknownName(String),
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     r: u8,
     g: u8,
     b: u8,
+    a: u8,
 }
 
+/// The named colors of CSS, as `0xRRGGBB`.
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
 impl Color {
     pub fn r(&self) -> u8 {
         self.r
@@ -29,19 +189,127 @@ impl Color {
     pub fn b(&self) -> u8 {
         self.b
     }
+
+    pub fn a(&self) -> u8 {
+        self.a
+    }
+
+    /// Formats the color as a CSS `rgba()` function.
+    pub fn to_css(&self) -> String {
+        format!(
+            "rgba({}, {}, {}, {})",
+            self.r,
+            self.g,
+            self.b,
+            self.a as f32 / 255.0
+        )
+    }
+
+    fn from_hex(hex: &str) -> Result<Self, ColorParseError> {
+        // Parsing a number would also take a sign, like `+f`.
+        if !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+            return Err(ColorParseError::InvalidFormat);
+        }
+        let component = |index: usize, width: usize| -> Result<u8, ColorParseError> {
+            let value = u8::from_str_radix(&hex[index * width..(index + 1) * width], 16)?;
+            // A single digit is repeated, so `f` is the same as `ff`.
+            Ok(if width == 1 { value * 17 } else { value })
+        };
+        let width = match hex.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return Err(ColorParseError::InvalidFormat),
+        };
+        Ok(Self {
+            r: component(0, width)?,
+            g: component(1, width)?,
+            b: component(2, width)?,
+            a: if matches!(hex.len(), 4 | 8) {
+                component(3, width)?
+            } else {
+                u8::MAX
+            },
+        })
+    }
+
+    /// Parses the arguments of an `rgb()` or `rgba()` function, which can be separated by commas or
+    /// spaces, with the alpha optionally after a `/`.
+    fn from_function(arguments: &str) -> Result<Self, ColorParseError> {
+        let arguments: Vec<&str> = arguments
+            .split([',', ' ', '/'])
+            .filter(|argument| !argument.is_empty())
+            .collect();
+        let (rgb, alpha) = match arguments[..] {
+            [r, g, b] => ([r, g, b], None),
+            [r, g, b, a] => ([r, g, b], Some(a)),
+            _ => return Err(ColorParseError::InvalidFormat),
+        };
+        Ok(Self {
+            r: rgb[0].parse()?,
+            g: rgb[1].parse()?,
+            b: rgb[2].parse()?,
+            a: alpha.map_or(Ok(u8::MAX), parse_alpha)?,
+        })
+    }
+}
+
+/// Parses an alpha from 0 to 1, or from 0% to 100%.
+fn parse_alpha(alpha: &str) -> Result<u8, ColorParseError> {
+    let (alpha, range) = match alpha.strip_suffix('%') {
+        Some(percentage) => (percentage, 100.0),
+        None => (alpha, 1.0),
+    };
+    let alpha: f32 = alpha.parse().map_err(|_| ColorParseError::InvalidAlpha)?;
+    if !(0.0..=range).contains(&alpha) {
+        return Err(ColorParseError::InvalidAlpha);
+    }
+    Ok((alpha / range * 255.0).round() as u8)
 }
 
 impl FromStr for Color {
     type Err = ColorParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            return Self::from_hex(hex);
+        }
+        let lowercase = s.to_ascii_lowercase();
+        if let Some(arguments) = lowercase
+            .strip_prefix("rgba(")
+            .or_else(|| lowercase.strip_prefix("rgb("))
+        {
+            return match arguments.strip_suffix(')') {
+                Some(arguments) => Self::from_function(arguments),
+                None => Err(ColorParseError::InvalidFormat),
+            };
+        }
+        if lowercase == "transparent" {
+            return Ok(Self::from((0, 0, 0, 0)));
+        }
+        if lowercase.starts_with(|character: char| character.is_ascii_alphabetic()) {
+            return NAMED_COLORS
+                .iter()
+                .find(|(name, _)| *name == lowercase)
+                .map(|(_, rgb)| {
+                    let [_, r, g, b] = rgb.to_be_bytes();
+                    Self::from((r, g, b))
+                })
+                .ok_or(ColorParseError::UnknownName(lowercase));
+        }
+
         let split: Vec<&str> = s.split_whitespace().collect();
         if split.len() == 3 {
             let r = split[0].parse()?;
             let g = split[1].parse()?;
             let b = split[2].parse()?;
 
-            Ok(Self { r, g, b })
+            Ok(Self {
+                r,
+                g,
+                b,
+                a: u8::MAX,
+            })
         } else {
             Err(ColorParseError::InvalidFormat)
         }
@@ -54,6 +322,148 @@ impl From<(u8, u8, u8)> for Color {
             r: value.0,
             g: value.1,
             b: value.2,
+            a: u8::MAX,
+        }
+    }
+}
+
+impl From<(u8, u8, u8, u8)> for Color {
+    fn from(value: (u8, u8, u8, u8)) -> Self {
+        Self {
+            r: value.0,
+            g: value.1,
+            b: value.2,
+            a: value.3,
+        }
+    }
+}
+
+/// Colors are written the same way in config files as on the command line.
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_css())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(color: &str) -> Color {
+        color
+            .parse()
+            .unwrap_or_else(|error| panic!("'{color}' should parse: {error}"))
+    }
+
+    #[test]
+    fn hex_colors_have_three_to_eight_digits() {
+        assert_eq!(parse("#ff8000"), Color::from((255, 128, 0)));
+        assert_eq!(parse("#FF800080"), Color::from((255, 128, 0, 128)));
+        assert_eq!(parse("#f80"), Color::from((255, 136, 0)));
+        assert_eq!(parse("#f808"), Color::from((255, 136, 0, 136)));
+    }
+
+    #[test]
+    fn hex_colors_only_take_hex_digits() {
+        for color in [
+            "#",
+            "#ff",
+            "#fffff",
+            "#fffffff",
+            "#fffffffff",
+            "#+ff",
+            "#+f+f+f",
+            "#-fffff",
+            "#ggg",
+            "#ff 000",
+            "#ffé0",
+        ] {
+            assert!(
+                matches!(color.parse::<Color>(), Err(ColorParseError::InvalidFormat)),
+                "'{color}' should be rejected"
+            );
         }
     }
+
+    #[test]
+    fn rgb_functions_take_commas_or_spaces() {
+        assert_eq!(parse("rgb(255, 128, 0)"), Color::from((255, 128, 0)));
+        assert_eq!(parse("RGB(255 128 0)"), Color::from((255, 128, 0)));
+        assert_eq!(
+            parse("rgba(255, 128, 0, 0.5)"),
+            Color::from((255, 128, 0, 128))
+        );
+        assert_eq!(
+            parse("rgb(255 128 0 / 50%)"),
+            Color::from((255, 128, 0, 128))
+        );
+        assert_eq!(parse("rgba(0, 0, 0, 0)"), Color::from((0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn rgb_functions_are_checked() {
+        assert!(matches!(
+            "rgb(255, 128)".parse::<Color>(),
+            Err(ColorParseError::InvalidFormat)
+        ));
+        assert!(matches!(
+            "rgb(255, 128, 0".parse::<Color>(),
+            Err(ColorParseError::InvalidFormat)
+        ));
+        assert!(matches!(
+            "rgb(256, 0, 0)".parse::<Color>(),
+            Err(ColorParseError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "rgba(0, 0, 0, 1.5)".parse::<Color>(),
+            Err(ColorParseError::InvalidAlpha)
+        ));
+        assert!(matches!(
+            "rgba(0, 0, 0, 101%)".parse::<Color>(),
+            Err(ColorParseError::InvalidAlpha)
+        ));
+        assert!(matches!(
+            "rgba(0, 0, 0, half)".parse::<Color>(),
+            Err(ColorParseError::InvalidAlpha)
+        ));
+    }
+
+    #[test]
+    fn css_names_are_known() {
+        assert_eq!(parse("green"), Color::from((0, 128, 0)));
+        assert_eq!(parse(" RebeccaPurple "), Color::from((102, 51, 153)));
+        assert_eq!(parse("transparent"), Color::from((0, 0, 0, 0)));
+        assert!(matches!(
+            "greenish".parse::<Color>(),
+            Err(ColorParseError::UnknownName(name)) if name == "greenish"
+        ));
+    }
+
+    #[test]
+    fn components_can_be_separated_by_spaces() {
+        assert_eq!(parse("255 128 0"), Color::from((255, 128, 0)));
+        assert!(matches!(
+            "255 128".parse::<Color>(),
+            Err(ColorParseError::InvalidFormat)
+        ));
+        assert!(matches!(
+            "255 128 300".parse::<Color>(),
+            Err(ColorParseError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn colors_are_written_the_way_they_are_read() {
+        let color = Color::from((255, 128, 0, 128));
+        assert_eq!(color.to_css(), "rgba(255, 128, 0, 0.5019608)");
+        assert_eq!(parse(&color.to_css()), color);
+    }
 }
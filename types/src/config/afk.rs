@@ -0,0 +1,42 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+use super::image::ImageSource;
+
+#[serde_inline_default]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AfkConfig {
+    /// How long, in seconds, there has to be no speech or input before the avatar falls asleep.
+    #[default(120)]
+    #[serde_inline_default(120)]
+    timeout: u64,
+    /// The frames of the sleeping animation. A single image is shown as a still.
+    sleeping_images: Vec<ImageSource>,
+    /// How long, in milliseconds, each sleeping frame is shown for.
+    #[default(1000)]
+    #[serde_inline_default(1000)]
+    frame_duration: u64,
+}
+
+impl AfkConfig {
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    pub fn sleeping_images(&self) -> &Vec<ImageSource> {
+        &self.sleeping_images
+    }
+
+    pub fn frame_duration(&self) -> u64 {
+        self.frame_duration
+    }
+
+    pub(crate) fn image_sources(&self) -> Vec<&ImageSource> {
+        self.sleeping_images.iter().collect()
+    }
+
+    pub(crate) fn image_sources_mut(&mut self) -> Vec<&mut ImageSource> {
+        self.sleeping_images.iter_mut().collect()
+    }
+}
@@ -0,0 +1,66 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+use super::image::ImageSource;
+
+#[serde_inline_default]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BlinkConfig {
+    /// Eyes closed variants of the idle images, one for each section. This can be left empty when
+    /// only layers change while blinking.
+    #[serde(default)]
+    idle_images: Vec<ImageSource>,
+    /// Eyes closed variants of the speaking images. If this is empty, the avatar does not blink
+    /// while speaking.
+    #[serde(default)]
+    speaking_images: Vec<ImageSource>,
+    /// The shortest time, in milliseconds, between two blinks.
+    #[default(2000)]
+    #[serde_inline_default(2000)]
+    min_interval: u64,
+    /// The longest time, in milliseconds, between two blinks.
+    #[default(6000)]
+    #[serde_inline_default(6000)]
+    max_interval: u64,
+    /// How long, in milliseconds, the eyes stay closed.
+    #[default(150)]
+    #[serde_inline_default(150)]
+    duration: u64,
+}
+
+impl BlinkConfig {
+    pub fn idle_images(&self) -> &Vec<ImageSource> {
+        &self.idle_images
+    }
+
+    pub fn speaking_images(&self) -> &Vec<ImageSource> {
+        &self.speaking_images
+    }
+
+    pub(crate) fn image_sources(&self) -> Vec<&ImageSource> {
+        self.idle_images
+            .iter()
+            .chain(&self.speaking_images)
+            .collect()
+    }
+
+    pub(crate) fn image_sources_mut(&mut self) -> Vec<&mut ImageSource> {
+        self.idle_images
+            .iter_mut()
+            .chain(&mut self.speaking_images)
+            .collect()
+    }
+
+    pub fn min_interval(&self) -> u64 {
+        self.min_interval
+    }
+
+    pub fn max_interval(&self) -> u64 {
+        self.max_interval
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+}
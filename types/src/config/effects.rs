@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+use crate::{ArgsError, Color, ValidArgs};
+
+/// A solid background color that is easy to key out in capture software.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChromaKey {
+    Green,
+    Blue,
+    Magenta,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct OutlineConfig {
+    #[default(Color::from((255, 255, 255)))]
+    #[serde_inline_default(Color::from((255, 255, 255)))]
+    color: Color,
+    /// How thick the outline is, in pixels of the avatar's images.
+    #[default(2.0)]
+    #[serde_inline_default(2.0)]
+    width: f32,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct ShadowConfig {
+    #[default(Color::from((0, 0, 0, 128)))]
+    #[serde_inline_default(Color::from((0, 0, 0, 128)))]
+    color: Color,
+    /// How far the shadow is moved right and down, in pixels of the avatar's images.
+    #[default((4.0, 4.0))]
+    #[serde_inline_default((4.0, 4.0))]
+    offset: (f32, f32),
+    /// The blur radius of the shadow, in pixels of the avatar's images. This is the same as the
+    /// blur radius of a CSS drop shadow.
+    #[default(4.0)]
+    #[serde_inline_default(4.0)]
+    blur: f32,
+}
+
+/// Effects drawn around the avatar, following the outline of its images.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct EffectsConfig {
+    /// Replaces the background color with a chroma key color when set.
+    #[serde(default)]
+    chroma_key: Option<ChromaKey>,
+    #[serde(default)]
+    outline: Option<OutlineConfig>,
+    #[serde(default)]
+    shadow: Option<ShadowConfig>,
+}
+
+impl ChromaKey {
+    pub fn color(&self) -> Color {
+        match self {
+            ChromaKey::Green => Color::from((0, 255, 0)),
+            ChromaKey::Blue => Color::from((0, 0, 255)),
+            ChromaKey::Magenta => Color::from((255, 0, 255)),
+        }
+    }
+}
+
+impl FromStr for ChromaKey {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "green" => Ok(ChromaKey::Green),
+            "blue" => Ok(ChromaKey::Blue),
+            "magenta" => Ok(ChromaKey::Magenta),
+            _ => Err(ArgsError::InvalidValue(String::from(s), "chroma key")),
+        }
+    }
+}
+
+impl OutlineConfig {
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+}
+
+impl ShadowConfig {
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn offset(&self) -> (f32, f32) {
+        self.offset
+    }
+
+    pub fn blur(&self) -> f32 {
+        self.blur
+    }
+}
+
+impl EffectsConfig {
+    pub fn chroma_key(&self) -> Option<ChromaKey> {
+        self.chroma_key
+    }
+
+    pub fn outline(&self) -> Option<OutlineConfig> {
+        self.outline
+    }
+
+    pub fn shadow(&self) -> Option<ShadowConfig> {
+        self.shadow
+    }
+
+    /// Checks if anything is drawn around the avatar.
+    pub fn is_drawn(&self) -> bool {
+        self.outline.is_some() || self.shadow.is_some()
+    }
+
+    /// How far the effects reach past the edges of an image, in pixels of the image.
+    pub fn margin(&self) -> f32 {
+        let outline = self.outline.map_or(0.0, |outline| outline.width.max(0.0));
+        // A gaussian blur fades out at about three standard deviations, which is one and a half
+        // times the blur radius.
+        let shadow = self.shadow.map_or(0.0, |shadow| {
+            shadow.blur.max(0.0) * 1.5 + shadow.offset.0.abs().max(shadow.offset.1.abs())
+        });
+        outline + shadow
+    }
+
+    /// Replaces any option that was also passed on the command line.
+    pub fn with_args(mut self, args: &ValidArgs) -> Self {
+        self.chroma_key = args.chroma_key().or(self.chroma_key);
+        self
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::image::ImageSource;
+
+/// An alternate set of images, such as a happy or angry face, that can be switched to.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ExpressionConfig {
+    /// A global key combination that switches to this expression, like `Ctrl+Shift+1`. Pressing
+    /// it again while the expression is active switches back to the default.
+    #[serde(default)]
+    hotkey: Option<String>,
+    /// Images used instead of the idle images, one for each section.
+    #[serde(default)]
+    idle_images: Vec<ImageSource>,
+    /// Images used instead of the speaking images, one for each section. If this is empty, the
+    /// normal speaking images are used.
+    #[serde(default)]
+    speaking_images: Vec<ImageSource>,
+    /// Images used instead of the images of a layer, keyed by the layer's name. Each takes either
+    /// a single image that is used for every section, or one image for each section.
+    #[serde(default)]
+    layers: HashMap<String, Vec<ImageSource>>,
+    /// How long, in milliseconds, the expression stays active before switching back to the
+    /// default. When this is not set, the expression stays until it is switched away from.
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+impl ExpressionConfig {
+    pub fn hotkey(&self) -> Option<&str> {
+        self.hotkey.as_deref()
+    }
+
+    pub fn idle_images(&self) -> &Vec<ImageSource> {
+        &self.idle_images
+    }
+
+    pub fn speaking_images(&self) -> &Vec<ImageSource> {
+        &self.speaking_images
+    }
+
+    pub fn layers(&self) -> &HashMap<String, Vec<ImageSource>> {
+        &self.layers
+    }
+
+    pub(crate) fn image_sources(&self) -> Vec<&ImageSource> {
+        self.idle_images
+            .iter()
+            .chain(&self.speaking_images)
+            .chain(self.layers.values().flatten())
+            .collect()
+    }
+
+    pub(crate) fn image_sources_mut(&mut self) -> Vec<&mut ImageSource> {
+        self.idle_images
+            .iter_mut()
+            .chain(&mut self.speaking_images)
+            .chain(self.layers.values_mut().flatten())
+            .collect()
+    }
+
+    pub fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
+    /// Gets the image that replaces a layer's image for a section, if this expression replaces
+    /// that layer.
+    pub fn layer_image(&self, layer: &str, section: usize) -> Option<&ImageSource> {
+        let images = self.layers.get(layer)?;
+        if images.len() == 1 {
+            images.first()
+        } else {
+            images.get(section)
+        }
+    }
+}
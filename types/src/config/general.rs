@@ -4,18 +4,136 @@ use serde::{Deserialize, Serialize};
 
 use crate::TwoInts;
 
-use super::audio::AudioConfig;
+use super::{
+    afk::AfkConfig,
+    audio::AudioConfig,
+    blink::BlinkConfig,
+    effects::EffectsConfig,
+    expression::ExpressionConfig,
+    idle::IdleActionsConfig,
+    image::{ImageDirectory, ImageSource, SpriteSheet},
+    layer::LayerConfig,
+    motion::MotionConfig,
+    prop::PropConfig,
+    reaction::ClickReactionsConfig,
+    tracking::TrackingConfig,
+    transition::TransitionConfig,
+    typing::TypingConfig,
+    window::WindowConfig,
+};
+
+/// Collects every image slot of a config, from the config itself and from each of its sections.
+/// This is the only walk over the slots, so that the shared and mutable ones can't disagree. The
+/// section and prop methods are passed in since those differ between the two, the fields are
+/// borrowed separately so that the rest of the config stays available.
+macro_rules! image_sources {
+    ($config:ident, $sources:ident, $prop_source:ident $(, $mut:tt)?) => {{
+        let mut image_sources = Vec::new();
+        image_sources.extend(&$($mut)? $config.idle_images);
+        image_sources.extend(&$($mut)? $config.speaking_images);
+        for layer in &$($mut)? $config.layers {
+            image_sources.extend(layer.$sources());
+        }
+        if let Some(outside_images) = &$($mut)? $config.outside_images {
+            image_sources.push(&$($mut)? outside_images.idle);
+            image_sources.push(&$($mut)? outside_images.speaking);
+        }
+        if let Some(typing) = &$($mut)? $config.typing {
+            image_sources.extend(typing.$sources());
+        }
+        if let Some(afk) = &$($mut)? $config.afk {
+            image_sources.extend(afk.$sources());
+        }
+        if let Some(blink) = &$($mut)? $config.blink {
+            image_sources.extend(blink.$sources());
+        }
+        if let Some(idle_actions) = &$($mut)? $config.idle_actions {
+            image_sources.extend(idle_actions.$sources());
+        }
+        for (_, expression) in &$($mut)? $config.expressions {
+            image_sources.extend(expression.$sources());
+        }
+        if let Some(click_reactions) = &$($mut)? $config.click_reactions {
+            image_sources.extend(click_reactions.$sources());
+        }
+        for prop in &$($mut)? $config.props {
+            image_sources.push(prop.$prop_source());
+        }
+        image_sources
+    }};
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     audio: AudioConfig,
+    #[serde(default)]
+    tracking: TrackingConfig,
+    #[serde(default)]
+    transition: TransitionConfig,
+    #[serde(default)]
+    window: WindowConfig,
+    #[serde(default)]
+    effects: EffectsConfig,
     sections: (i32, i32),
-    idle_images: Vec<PathBuf>,
-    speaking_images: Vec<PathBuf>,
+    /// The full images for each section. These can be left empty when the avatar is made of
+    /// layers, otherwise they are drawn below the layers.
+    #[serde(default)]
+    idle_images: Vec<ImageSource>,
+    #[serde(default)]
+    speaking_images: Vec<ImageSource>,
+    /// A sprite sheet the idle images are sliced from, instead of listing them.
+    #[serde(default)]
+    idle_sheet: Option<SpriteSheet>,
+    /// A sprite sheet the speaking images are sliced from, instead of listing them.
+    #[serde(default)]
+    speaking_sheet: Option<SpriteSheet>,
+    /// A directory the idle and speaking images are found in by their names, instead of listing
+    /// them.
+    #[serde(default)]
+    image_directory: Option<ImageDirectory>,
+    /// Layers that are composited on top of each other, from bottom to top.
+    #[serde(default)]
+    layers: Vec<LayerConfig>,
+    /// Images shown while the cursor is outside of the configured screen area. When this is not
+    /// set, the cursor position is clamped into the closest section instead.
+    #[serde(default)]
+    outside_images: Option<OutsideImages>,
+    /// Enables typing detection when set. Keystrokes are only counted, which keys are pressed is
+    /// never looked at.
+    #[serde(default)]
+    typing: Option<TypingConfig>,
+    /// Enables AFK detection when set, putting the avatar to sleep after a while without input.
+    #[serde(default)]
+    afk: Option<AfkConfig>,
+    /// Images briefly shown in reaction to mouse clicks.
+    #[serde(default)]
+    click_reactions: Option<ClickReactionsConfig>,
+    /// Enables automatic blinking when set.
+    #[serde(default)]
+    blink: Option<BlinkConfig>,
+    /// Enables randomly picked idle actions when set.
+    #[serde(default)]
+    idle_actions: Option<IdleActionsConfig>,
+    /// Named alternate image sets that can be switched between at runtime.
+    #[serde(default)]
+    expressions: HashMap<String, ExpressionConfig>,
+    /// Makes the avatar bounce and squash while speaking when set.
+    #[serde(default)]
+    motion: Option<MotionConfig>,
+    /// Accessories that are drawn over the avatar and can be shown or hidden at runtime, from
+    /// bottom to top.
+    #[serde(default)]
+    props: Vec<PropConfig>,
     screen_information: ScreenInformation,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutsideImages {
+    idle: ImageSource,
+    speaking: ImageSource,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ScreenInformation {
     size: TwoInts,
@@ -27,24 +145,153 @@ impl Config {
         self.audio
     }
 
+    pub fn tracking(&self) -> TrackingConfig {
+        self.tracking
+    }
+
+    pub fn transition(&self) -> TransitionConfig {
+        self.transition
+    }
+
+    pub fn window(&self) -> WindowConfig {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: WindowConfig) {
+        self.window = window;
+    }
+
+    pub fn effects(&self) -> EffectsConfig {
+        self.effects
+    }
+
+    pub fn set_effects(&mut self, effects: EffectsConfig) {
+        self.effects = effects;
+    }
+
     pub fn sections(&self) -> (i32, i32) {
         self.sections
     }
 
-    pub fn idle_images(&self) -> &Vec<PathBuf> {
+    pub fn idle_images(&self) -> &Vec<ImageSource> {
         &self.idle_images
     }
 
-    pub fn speaking_images(&self) -> &Vec<PathBuf> {
+    pub fn speaking_images(&self) -> &Vec<ImageSource> {
         &self.speaking_images
     }
 
+    pub fn set_idle_images(&mut self, idle_images: Vec<ImageSource>) {
+        self.idle_images = idle_images;
+    }
+
+    pub fn set_speaking_images(&mut self, speaking_images: Vec<ImageSource>) {
+        self.speaking_images = speaking_images;
+    }
+
+    pub fn idle_sheet(&self) -> Option<&SpriteSheet> {
+        self.idle_sheet.as_ref()
+    }
+
+    pub fn speaking_sheet(&self) -> Option<&SpriteSheet> {
+        self.speaking_sheet.as_ref()
+    }
+
+    pub fn image_directory(&self) -> Option<&ImageDirectory> {
+        self.image_directory.as_ref()
+    }
+
+    pub fn layers(&self) -> &Vec<LayerConfig> {
+        &self.layers
+    }
+
+    pub fn outside_images(&self) -> Option<&OutsideImages> {
+        self.outside_images.as_ref()
+    }
+
+    pub fn typing(&self) -> Option<&TypingConfig> {
+        self.typing.as_ref()
+    }
+
+    pub fn afk(&self) -> Option<&AfkConfig> {
+        self.afk.as_ref()
+    }
+
+    pub fn click_reactions(&self) -> Option<&ClickReactionsConfig> {
+        self.click_reactions.as_ref()
+    }
+
+    pub fn blink(&self) -> Option<&BlinkConfig> {
+        self.blink.as_ref()
+    }
+
+    pub fn idle_actions(&self) -> Option<&IdleActionsConfig> {
+        self.idle_actions.as_ref()
+    }
+
+    pub fn expressions(&self) -> &HashMap<String, ExpressionConfig> {
+        &self.expressions
+    }
+
+    pub fn expression(&self, name: &str) -> Option<&ExpressionConfig> {
+        self.expressions.get(name)
+    }
+
+    pub fn motion(&self) -> Option<MotionConfig> {
+        self.motion
+    }
+
+    pub fn props(&self) -> &Vec<PropConfig> {
+        &self.props
+    }
+
+    pub fn prop(&self, name: &str) -> Option<&PropConfig> {
+        self.props.iter().find(|prop| prop.name() == name)
+    }
+
+    /// Gets every image source used anywhere in the config. Sources that are used in multiple
+    /// places are returned multiple times.
+    pub fn image_sources(&self) -> Vec<&ImageSource> {
+        image_sources!(self, image_sources, image)
+    }
+
+    /// Gets the path of every image file used anywhere in the config.
+    pub fn image_paths(&self) -> Vec<&PathBuf> {
+        self.image_sources()
+            .into_iter()
+            .flat_map(ImageSource::paths)
+            .collect()
+    }
+
+    /// Gets every path in the config that points at an asset, so that they can be resolved. This
+    /// includes sprite sheets, their atlases and the image directory, unlike [`Self::image_paths`].
+    pub fn asset_paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        let image_sources: Vec<&mut ImageSource> =
+            image_sources!(self, image_sources_mut, image_source_mut, mut);
+        let mut paths: Vec<&mut PathBuf> = image_sources
+            .into_iter()
+            .flat_map(ImageSource::paths_mut)
+            .collect();
+        for sheet in [&mut self.idle_sheet, &mut self.speaking_sheet]
+            .into_iter()
+            .flatten()
+        {
+            paths.extend(sheet.paths_mut());
+        }
+        if let Some(image_directory) = &mut self.image_directory {
+            paths.push(image_directory.path_mut());
+        }
+        paths
+    }
+
     pub fn total_sections(&self) -> usize {
         (self.sections.0 * self.sections.1) as usize
     }
 
+    /// The amount of images that can be used for a section, which is the smaller of the idle and
+    /// speaking image counts.
     pub fn image_count(&self) -> usize {
-        self.idle_images.len()
+        self.idle_images.len().min(self.speaking_images.len())
     }
 
     pub fn screen_information(&self) -> &ScreenInformation {
@@ -52,6 +299,16 @@ impl Config {
     }
 }
 
+impl OutsideImages {
+    pub fn idle(&self) -> &ImageSource {
+        &self.idle
+    }
+
+    pub fn speaking(&self) -> &ImageSource {
+        &self.speaking
+    }
+}
+
 impl ScreenInformation {
     pub fn size(&self) -> TwoInts {
         self.size
@@ -61,3 +318,105 @@ impl ScreenInformation {
         *self.modifiers.get(os).unwrap_or(&TwoInts::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config with a single image in every slot, named after that slot.
+    fn config() -> Config {
+        serde_json::from_value(serde_json::json!({
+            "sections": [1, 1],
+            "idle_images": ["idle"],
+            "speaking_images": ["speaking"],
+            "idle_sheet": { "path": "idle_sheet", "grid": [1, 1] },
+            "image_directory": { "path": "directory", "idle": "idle", "speaking": "speaking" },
+            "layers": [{
+                "name": "layer",
+                "images": ["layer"],
+                "speaking_images": ["layer_speaking"],
+                "blinking_images": ["layer_blinking"],
+            }],
+            "outside_images": { "idle": "outside_idle", "speaking": "outside_speaking" },
+            "typing": {
+                "idle_images": ["typing_idle"],
+                "speaking_images": ["typing_speaking"],
+                "blinking_idle_images": ["typing_blinking_idle"],
+                "blinking_speaking_images": ["typing_blinking_speaking"],
+            },
+            "afk": { "sleeping_images": ["sleeping"] },
+            "click_reactions": {
+                "left": { "images": ["left_click"] },
+                "right": { "images": ["right_click"] },
+            },
+            "blink": { "idle_images": ["blink_idle"], "speaking_images": ["blink_speaking"] },
+            "idle_actions": { "actions": [{ "name": "wave", "images": ["wave"] }] },
+            "expressions": {
+                "happy": {
+                    "idle_images": ["happy_idle"],
+                    "speaking_images": ["happy_speaking"],
+                    "layers": { "layer": ["happy_layer"] },
+                },
+            },
+            "props": [{ "name": "hat", "image": "hat" }],
+            "screen_information": { "size": [100, 100], "modifiers": {} },
+        }))
+        .unwrap()
+    }
+
+    fn sorted(paths: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = paths.into_iter().collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn every_image_slot_is_walked() {
+        let slots = [
+            "idle",
+            "speaking",
+            "layer",
+            "layer_speaking",
+            "layer_blinking",
+            "outside_idle",
+            "outside_speaking",
+            "typing_idle",
+            "typing_speaking",
+            "typing_blinking_idle",
+            "typing_blinking_speaking",
+            "sleeping",
+            "left_click",
+            "right_click",
+            "blink_idle",
+            "blink_speaking",
+            "wave",
+            "happy_idle",
+            "happy_speaking",
+            "happy_layer",
+            "hat",
+        ];
+        assert_eq!(
+            sorted(config().image_paths().into_iter().cloned()),
+            sorted(slots.map(PathBuf::from))
+        );
+    }
+
+    #[test]
+    fn asset_paths_are_the_image_paths_plus_sheets_and_directories() {
+        let mut config = config();
+        let image_paths = sorted(config.image_paths().into_iter().cloned());
+        let mut asset_paths = sorted(
+            config
+                .asset_paths_mut()
+                .into_iter()
+                .map(|path| path.clone()),
+        );
+        for extra in ["directory", "idle_sheet"] {
+            let index = asset_paths
+                .iter()
+                .position(|path| path.as_os_str() == extra);
+            asset_paths.remove(index.unwrap());
+        }
+        assert_eq!(asset_paths, image_paths);
+    }
+}
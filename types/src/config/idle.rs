@@ -0,0 +1,102 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+use super::image::ImageSource;
+
+/// Plays a randomly picked idle action, such as looking around or yawning, every so often while
+/// the user is quiet and the cursor is still.
+#[serde_inline_default]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IdleActionsConfig {
+    actions: Vec<IdleAction>,
+    /// The shortest time, in milliseconds, between two idle actions.
+    #[default(15000)]
+    #[serde_inline_default(15000)]
+    min_interval: u64,
+    /// The longest time, in milliseconds, between two idle actions.
+    #[default(45000)]
+    #[serde_inline_default(45000)]
+    max_interval: u64,
+    /// How long, in milliseconds, there has to be no speaking or cursor movement before an idle
+    /// action plays.
+    #[default(2000)]
+    #[serde_inline_default(2000)]
+    quiet_time: u64,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IdleAction {
+    name: String,
+    /// The image for each section. If only one image is given, it is used for every section.
+    /// Animations that play once start from the beginning whenever the action plays.
+    images: Vec<ImageSource>,
+    /// How likely this action is picked compared to the others.
+    #[default(1.0)]
+    #[serde_inline_default(1.0)]
+    weight: f32,
+    /// How long, in milliseconds, the action plays for.
+    #[default(2000)]
+    #[serde_inline_default(2000)]
+    duration: u64,
+}
+
+impl IdleActionsConfig {
+    pub fn actions(&self) -> &Vec<IdleAction> {
+        &self.actions
+    }
+
+    pub(crate) fn image_sources(&self) -> Vec<&ImageSource> {
+        self.actions
+            .iter()
+            .flat_map(|action| &action.images)
+            .collect()
+    }
+
+    pub(crate) fn image_sources_mut(&mut self) -> Vec<&mut ImageSource> {
+        self.actions
+            .iter_mut()
+            .flat_map(|action| &mut action.images)
+            .collect()
+    }
+
+    pub fn min_interval(&self) -> u64 {
+        self.min_interval
+    }
+
+    pub fn max_interval(&self) -> u64 {
+        self.max_interval
+    }
+
+    pub fn quiet_time(&self) -> u64 {
+        self.quiet_time
+    }
+}
+
+impl IdleAction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn images(&self) -> &Vec<ImageSource> {
+        &self.images
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    /// Gets the image of the action for a section.
+    pub fn image(&self, section: usize) -> Option<&ImageSource> {
+        if self.images.len() == 1 {
+            self.images.first()
+        } else {
+            self.images.get(section)
+        }
+    }
+}
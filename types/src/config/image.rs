@@ -0,0 +1,211 @@
+use std::path::PathBuf;
+
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+/// Anything that can be shown in an image slot. This is either the path to a single image, which
+/// may itself be animated, a sequence of frames or a single cell of a sprite sheet.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ImageSource {
+    Path(PathBuf),
+    Sequence(FrameSequence),
+    Cell(SheetCell),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FrameSequence {
+    frames: Vec<SequenceFrame>,
+    #[serde(default)]
+    mode: LoopMode,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SequenceFrame {
+    path: PathBuf,
+    /// How long, in milliseconds, the frame is shown for.
+    #[default(100)]
+    #[serde_inline_default(100)]
+    duration: u64,
+}
+
+/// A rectangle cut out of a sprite sheet, in pixels.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SheetCell {
+    sheet: PathBuf,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// A single image that holds many images. The cells are sliced left to right, then top to bottom,
+/// which is the same order sections are numbered in.
+///
+/// Exactly one of `cell_size`, `grid` or `atlas` has to be set.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SpriteSheet {
+    path: PathBuf,
+    /// The width and height of every cell, in pixels.
+    #[serde(default)]
+    cell_size: Option<(u32, u32)>,
+    /// The amount of columns and rows the sheet is split into.
+    #[serde(default)]
+    grid: Option<(u32, u32)>,
+    /// A TexturePacker style JSON atlas describing irregular cells. Cells are used in the order
+    /// they are listed in, whether the frames are given as an array or as an object. Rotated and
+    /// trimmed frames are not supported.
+    #[serde(default)]
+    atlas: Option<PathBuf>,
+}
+
+/// A directory the images for each section are found in by their name, instead of listing them.
+///
+/// The patterns are relative to the directory. `{x}` and `{y}` are replaced by the column and row
+/// of a section, and `{index}` by its number, so `idle/{x}-{y}.png` finds `idle/2-0.png` for the
+/// third section of the first row. Numbers can be zero padded by giving a width, like `{x:2}`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ImageDirectory {
+    path: PathBuf,
+    idle: String,
+    speaking: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    /// Starts over from the first frame after the last.
+    #[default]
+    Loop,
+    /// Plays forwards, then backwards, then forwards again.
+    PingPong,
+    /// Plays once from when the image is first shown, then holds the last frame.
+    Once,
+}
+
+impl ImageSource {
+    /// Gets the path of every image file this source uses.
+    pub fn paths(&self) -> Vec<&PathBuf> {
+        match self {
+            ImageSource::Path(path) => vec![path],
+            ImageSource::Sequence(sequence) => {
+                sequence.frames.iter().map(|frame| &frame.path).collect()
+            }
+            ImageSource::Cell(cell) => vec![&cell.sheet],
+        }
+    }
+
+    pub fn paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        match self {
+            ImageSource::Path(path) => vec![path],
+            ImageSource::Sequence(sequence) => sequence
+                .frames
+                .iter_mut()
+                .map(|frame| &mut frame.path)
+                .collect(),
+            ImageSource::Cell(cell) => vec![&mut cell.sheet],
+        }
+    }
+}
+
+impl From<PathBuf> for ImageSource {
+    fn from(value: PathBuf) -> Self {
+        Self::Path(value)
+    }
+}
+
+impl FrameSequence {
+    pub fn frames(&self) -> &Vec<SequenceFrame> {
+        &self.frames
+    }
+
+    pub fn mode(&self) -> LoopMode {
+        self.mode
+    }
+}
+
+impl SheetCell {
+    pub fn new(sheet: PathBuf, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            sheet,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn sheet(&self) -> &PathBuf {
+        &self.sheet
+    }
+
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl SpriteSheet {
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn cell_size(&self) -> Option<(u32, u32)> {
+        self.cell_size
+    }
+
+    pub fn grid(&self) -> Option<(u32, u32)> {
+        self.grid
+    }
+
+    pub fn atlas(&self) -> Option<&PathBuf> {
+        self.atlas.as_ref()
+    }
+
+    pub(crate) fn paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        let mut paths = vec![&mut self.path];
+        paths.extend(&mut self.atlas);
+        paths
+    }
+}
+
+impl ImageDirectory {
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub(crate) fn path_mut(&mut self) -> &mut PathBuf {
+        &mut self.path
+    }
+
+    pub fn idle(&self) -> &str {
+        &self.idle
+    }
+
+    pub fn speaking(&self) -> &str {
+        &self.speaking
+    }
+}
+
+impl SequenceFrame {
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use super::{image::ImageSource, spring::SpringConfig};
+
+/// A single layer of a layered avatar, such as the body, eyes, mouth, hair or an accessory.
+///
+/// Every image list takes either a single image that is used for every section, or one image for
+/// each section.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LayerConfig {
+    name: String,
+    /// The images shown while idle.
+    images: Vec<ImageSource>,
+    /// The images shown while speaking. If this is empty, the idle images are used instead.
+    #[serde(default)]
+    speaking_images: Vec<ImageSource>,
+    /// The images shown while blinking. If this is empty, the layer does not change when blinking.
+    #[serde(default)]
+    blinking_images: Vec<ImageSource>,
+    /// Makes the layer lag behind the rest of the avatar when set.
+    #[serde(default)]
+    spring: Option<SpringConfig>,
+}
+
+impl LayerConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn images(&self) -> &Vec<ImageSource> {
+        &self.images
+    }
+
+    pub fn speaking_images(&self) -> &Vec<ImageSource> {
+        &self.speaking_images
+    }
+
+    pub fn blinking_images(&self) -> &Vec<ImageSource> {
+        &self.blinking_images
+    }
+
+    pub fn spring(&self) -> Option<SpringConfig> {
+        self.spring
+    }
+
+    pub(crate) fn image_sources(&self) -> Vec<&ImageSource> {
+        self.images
+            .iter()
+            .chain(&self.speaking_images)
+            .chain(&self.blinking_images)
+            .collect()
+    }
+
+    pub(crate) fn image_sources_mut(&mut self) -> Vec<&mut ImageSource> {
+        self.images
+            .iter_mut()
+            .chain(&mut self.speaking_images)
+            .chain(&mut self.blinking_images)
+            .collect()
+    }
+
+    /// Gets the image this layer shows for a section. Blinking takes priority over speaking.
+    pub fn image(&self, section: usize, speaking: bool, blinking: bool) -> Option<&ImageSource> {
+        let images = if blinking && !self.blinking_images.is_empty() {
+            &self.blinking_images
+        } else if speaking && !self.speaking_images.is_empty() {
+            &self.speaking_images
+        } else {
+            &self.images
+        };
+        if images.len() == 1 {
+            images.first()
+        } else {
+            images.get(section)
+        }
+    }
+}
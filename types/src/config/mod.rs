@@ -1,5 +1,37 @@
+pub use afk::AfkConfig;
 pub use audio::AudioConfig;
+pub use blink::BlinkConfig;
+pub use effects::{ChromaKey, EffectsConfig, OutlineConfig, ShadowConfig};
+pub use expression::ExpressionConfig;
 pub use general::Config;
+pub use idle::{IdleAction, IdleActionsConfig};
+pub use image::{
+    FrameSequence, ImageDirectory, ImageSource, LoopMode, SequenceFrame, SheetCell, SpriteSheet,
+};
+pub use layer::LayerConfig;
+pub use motion::{Easing, MotionConfig, MotionDriver, Transform};
+pub use prop::PropConfig;
+pub use reaction::{ClickReactionsConfig, ReactionConfig};
+pub use spring::SpringConfig;
+pub use tracking::{TrackingConfig, TrackingMode};
+pub use transition::{TransitionConfig, TransitionKind};
+pub use typing::TypingConfig;
+pub use window::{Anchor, FitMode, WindowConfig};
 
+pub mod afk;
 pub mod audio;
+pub mod blink;
+pub mod effects;
+pub mod expression;
 pub mod general;
+pub mod idle;
+pub mod image;
+pub mod layer;
+pub mod motion;
+pub mod prop;
+pub mod reaction;
+pub mod spring;
+pub mod tracking;
+pub mod transition;
+pub mod typing;
+pub mod window;
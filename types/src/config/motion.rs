@@ -0,0 +1,211 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+/// Moves, scales and rotates the avatar depending on how loud the user is speaking.
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct MotionConfig {
+    /// The transform while silent.
+    #[serde(default)]
+    idle: Transform,
+    /// The transform at full volume. Volumes in between blend the two transforms.
+    #[serde(default)]
+    speaking: Transform,
+    /// How the volume is mapped onto the blend between the two transforms.
+    #[serde(default)]
+    easing: Easing,
+    #[serde(default)]
+    driver: MotionDriver,
+    /// How long, in milliseconds, the transform takes to mostly catch up with the volume. Zero
+    /// follows the volume immediately.
+    #[default(60)]
+    #[serde_inline_default(60)]
+    smoothing: u64,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    /// How far, in pixels, the avatar is moved up.
+    #[serde(default)]
+    offset: f32,
+    #[default(1.0)]
+    #[serde_inline_default(1.0)]
+    scale_x: f32,
+    #[default(1.0)]
+    #[serde_inline_default(1.0)]
+    scale_y: f32,
+    /// The clockwise rotation, in degrees.
+    #[serde(default)]
+    rotation: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+/// What is used as the volume that drives the motion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionDriver {
+    /// The speaking sensitivity, which jumps up when speaking and then slowly falls off.
+    #[default]
+    Sensitivity,
+    /// The raw audio magnitude, relative to the maximum magnitude.
+    Magnitude,
+}
+
+impl MotionConfig {
+    pub fn idle(&self) -> Transform {
+        self.idle
+    }
+
+    pub fn speaking(&self) -> Transform {
+        self.speaking
+    }
+
+    pub fn easing(&self) -> Easing {
+        self.easing
+    }
+
+    pub fn driver(&self) -> MotionDriver {
+        self.driver
+    }
+
+    pub fn smoothing(&self) -> u64 {
+        self.smoothing
+    }
+
+    /// Gets the transform for a volume between 0 and 1.
+    pub fn transform_at(&self, level: f32) -> Transform {
+        self.idle
+            .lerp(&self.speaking, self.easing.apply(level.clamp(0.0, 1.0)))
+    }
+}
+
+impl Transform {
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    pub fn scale_x(&self) -> f32 {
+        self.scale_x
+    }
+
+    pub fn scale_y(&self) -> f32 {
+        self.scale_y
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Blends this transform with another, where `amount` 0 is this transform and 1 is the other.
+    pub fn lerp(&self, other: &Transform, amount: f32) -> Transform {
+        let lerp = |from: f32, to: f32| from + (to - from) * amount;
+        Transform {
+            offset: lerp(self.offset, other.offset),
+            scale_x: lerp(self.scale_x, other.scale_x),
+            scale_y: lerp(self.scale_y, other.scale_y),
+            rotation: lerp(self.rotation, other.rotation),
+        }
+    }
+}
+
+impl Easing {
+    /// Eases a value between 0 and 1.
+    pub fn apply(&self, value: f32) -> f32 {
+        match self {
+            Easing::Linear => value,
+            Easing::EaseIn => value * value,
+            Easing::EaseOut => 1.0 - (1.0 - value) * (1.0 - value),
+            Easing::EaseInOut => {
+                if value < 0.5 {
+                    2.0 * value * value
+                } else {
+                    1.0 - 2.0 * (1.0 - value) * (1.0 - value)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 4] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ];
+
+    fn motion(easing: Easing) -> MotionConfig {
+        MotionConfig {
+            speaking: Transform {
+                offset: 10.0,
+                scale_x: 1.2,
+                scale_y: 0.8,
+                rotation: -20.0,
+            },
+            easing,
+            ..MotionConfig::default()
+        }
+    }
+
+    #[test]
+    fn every_easing_starts_at_zero_and_ends_at_one() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+            assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+        }
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn every_easing_only_goes_up() {
+        for easing in EASINGS {
+            let values: Vec<f32> = (0..=100)
+                .map(|step| easing.apply(step as f32 / 100.0))
+                .collect();
+            assert!(
+                values.windows(2).all(|pair| pair[0] <= pair[1]),
+                "{easing:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn transforms_go_from_idle_to_speaking() {
+        let motion = motion(Easing::Linear);
+        assert_eq!(motion.transform_at(0.0), motion.idle());
+        assert_eq!(motion.transform_at(1.0), motion.speaking());
+
+        let halfway = motion.transform_at(0.5);
+        assert_eq!(halfway.offset(), 5.0);
+        assert!((halfway.scale_x() - 1.1).abs() < 1e-6);
+        assert!((halfway.scale_y() - 0.9).abs() < 1e-6);
+        assert_eq!(halfway.rotation(), -10.0);
+    }
+
+    #[test]
+    fn transforms_are_eased() {
+        let motion = motion(Easing::EaseIn);
+        assert_eq!(motion.transform_at(0.5).offset(), 2.5);
+    }
+
+    #[test]
+    fn volumes_outside_of_zero_to_one_are_clamped() {
+        let motion = motion(Easing::EaseOut);
+        assert_eq!(motion.transform_at(-1.0), motion.idle());
+        assert_eq!(motion.transform_at(2.0), motion.speaking());
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use super::{image::ImageSource, spring::SpringConfig};
+
+/// An accessory, such as a hat or a headset, that is drawn over the avatar and can be shown or
+/// hidden at runtime. Whether a prop is shown is remembered across restarts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PropConfig {
+    name: String,
+    image: ImageSource,
+    /// How far the center of the prop is moved from the center of the avatar, in pixels of the
+    /// avatar's images. Takes either a single offset that is used for every section, or one offset
+    /// for each section. The prop is centered when this is empty.
+    #[serde(default)]
+    offsets: Vec<(f32, f32)>,
+    /// Whether the prop is shown before it has ever been toggled.
+    #[serde(default)]
+    shown: bool,
+    /// A global key combination that shows or hides this prop, like `Ctrl+Shift+H`.
+    #[serde(default)]
+    hotkey: Option<String>,
+    /// Makes the prop lag behind the rest of the avatar when set.
+    #[serde(default)]
+    spring: Option<SpringConfig>,
+}
+
+impl PropConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn image(&self) -> &ImageSource {
+        &self.image
+    }
+
+    pub fn offsets(&self) -> &Vec<(f32, f32)> {
+        &self.offsets
+    }
+
+    pub fn shown(&self) -> bool {
+        self.shown
+    }
+
+    pub fn hotkey(&self) -> Option<&str> {
+        self.hotkey.as_deref()
+    }
+
+    pub fn spring(&self) -> Option<SpringConfig> {
+        self.spring
+    }
+
+    pub(crate) fn image_source_mut(&mut self) -> &mut ImageSource {
+        &mut self.image
+    }
+
+    /// Gets how far the prop is moved for a section.
+    pub fn offset(&self, section: usize) -> (f32, f32) {
+        let offset = if self.offsets.len() == 1 {
+            self.offsets.first()
+        } else {
+            self.offsets.get(section)
+        };
+        offset.copied().unwrap_or_default()
+    }
+}
@@ -0,0 +1,70 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+use super::image::ImageSource;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ClickReactionsConfig {
+    #[serde(default)]
+    left: Option<ReactionConfig>,
+    #[serde(default)]
+    right: Option<ReactionConfig>,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReactionConfig {
+    /// The reaction image for each section. If only one image is given, it is used for every
+    /// section.
+    images: Vec<ImageSource>,
+    /// How long, in milliseconds, the reaction is shown for.
+    #[default(300)]
+    #[serde_inline_default(300)]
+    duration: u64,
+}
+
+impl ClickReactionsConfig {
+    pub fn left(&self) -> Option<&ReactionConfig> {
+        self.left.as_ref()
+    }
+
+    pub fn right(&self) -> Option<&ReactionConfig> {
+        self.right.as_ref()
+    }
+
+    pub(crate) fn image_sources(&self) -> Vec<&ImageSource> {
+        [&self.left, &self.right]
+            .into_iter()
+            .flatten()
+            .flat_map(|reaction| &reaction.images)
+            .collect()
+    }
+
+    pub(crate) fn image_sources_mut(&mut self) -> Vec<&mut ImageSource> {
+        [&mut self.left, &mut self.right]
+            .into_iter()
+            .flatten()
+            .flat_map(|reaction| &mut reaction.images)
+            .collect()
+    }
+}
+
+impl ReactionConfig {
+    pub fn images(&self) -> &Vec<ImageSource> {
+        &self.images
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    /// Gets the reaction image for a section.
+    pub fn image(&self, section: usize) -> Option<&ImageSource> {
+        if self.images.len() == 1 {
+            self.images.first()
+        } else {
+            self.images.get(section)
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+/// Makes a layer or prop lag behind and overshoot the rest of the avatar, like hair or ears, as if
+/// it was attached to the avatar by a spring.
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct SpringConfig {
+    /// How strongly the image is pulled back into place, up to 10000. Higher is faster and
+    /// bouncier.
+    #[default(120.0)]
+    #[serde_inline_default(120.0)]
+    stiffness: f32,
+    /// How quickly the image stops swinging. Zero never stops.
+    #[default(10.0)]
+    #[serde_inline_default(10.0)]
+    damping: f32,
+    /// How far the avatar is thought to move when the cursor moves one section right and down, in
+    /// pixels of the avatar's images. The image springs after this movement.
+    #[default((6.0, 4.0))]
+    #[serde_inline_default((6.0, 4.0))]
+    section_shift: (f32, f32),
+    /// How far the image can lag behind in any direction, in pixels of the avatar's images.
+    #[default(24.0)]
+    #[serde_inline_default(24.0)]
+    max_offset: f32,
+}
+
+impl SpringConfig {
+    pub fn stiffness(&self) -> f32 {
+        self.stiffness
+    }
+
+    pub fn damping(&self) -> f32 {
+        self.damping
+    }
+
+    pub fn section_shift(&self) -> (f32, f32) {
+        self.section_shift
+    }
+
+    pub fn max_offset(&self) -> f32 {
+        self.max_offset
+    }
+
+    /// Gets where the image is pulled towards while the cursor is in the section and the avatar is
+    /// moved up by `lift` pixels.
+    pub fn anchor(&self, (section_x, section_y): (i32, i32), lift: f32) -> (f32, f32) {
+        (
+            section_x as f32 * self.section_shift.0,
+            section_y as f32 * self.section_shift.1 - lift,
+        )
+    }
+}
@@ -0,0 +1,35 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingMode {
+    /// Reads the cursor position every poll interval.
+    Poll,
+    /// Only reads the cursor position when the mouse reports movement. This falls back to polling
+    /// if mouse movement can't be listened to.
+    #[default]
+    Event,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct TrackingConfig {
+    #[serde(default)]
+    mode: TrackingMode,
+    /// How often, in milliseconds, the cursor position is read while polling.
+    #[default(16)]
+    #[serde_inline_default(16)]
+    poll_interval: u64,
+}
+
+impl TrackingConfig {
+    pub fn mode(&self) -> TrackingMode {
+        self.mode
+    }
+
+    pub fn poll_interval(&self) -> u64 {
+        self.poll_interval
+    }
+}
@@ -0,0 +1,44 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// Switches images instantly.
+    #[default]
+    Cut,
+    /// Fades the old image out over the new one.
+    Crossfade,
+    /// Slides the old image out to the left while the new one slides in from the right.
+    Slide,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct TransitionConfig {
+    #[serde(default)]
+    kind: TransitionKind,
+    /// How long, in milliseconds, a transition takes.
+    #[default(150)]
+    #[serde_inline_default(150)]
+    duration: u64,
+    /// Blends the four sections closest to the cursor together, instead of only showing the
+    /// section the cursor is in.
+    #[serde(default)]
+    blend_sections: bool,
+}
+
+impl TransitionConfig {
+    pub fn kind(&self) -> TransitionKind {
+        self.kind
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    pub fn blend_sections(&self) -> bool {
+        self.blend_sections
+    }
+}
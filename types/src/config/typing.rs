@@ -0,0 +1,76 @@
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+use super::image::ImageSource;
+
+#[serde_inline_default]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TypingConfig {
+    /// How long, in milliseconds, typing stays active after the last keystroke.
+    #[default(400)]
+    #[serde_inline_default(400)]
+    timeout: u64,
+    /// The window, in milliseconds, that the key rate is measured over.
+    #[default(2000)]
+    #[serde_inline_default(2000)]
+    rate_window: u64,
+    /// Images used instead of the idle images while typing.
+    #[serde(default)]
+    idle_images: Vec<ImageSource>,
+    /// Images used instead of the speaking images while typing. If this is empty, the normal
+    /// speaking images are used.
+    #[serde(default)]
+    speaking_images: Vec<ImageSource>,
+    /// Eyes closed variants of the typing idle images. Without these, the avatar keeps its eyes
+    /// open while typing, so that blinking doesn't swap the typing images out.
+    #[serde(default)]
+    blinking_idle_images: Vec<ImageSource>,
+    /// Eyes closed variants of the typing speaking images.
+    #[serde(default)]
+    blinking_speaking_images: Vec<ImageSource>,
+}
+
+impl TypingConfig {
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    pub fn rate_window(&self) -> u64 {
+        self.rate_window
+    }
+
+    pub fn idle_images(&self) -> &Vec<ImageSource> {
+        &self.idle_images
+    }
+
+    pub fn speaking_images(&self) -> &Vec<ImageSource> {
+        &self.speaking_images
+    }
+
+    pub fn blinking_idle_images(&self) -> &Vec<ImageSource> {
+        &self.blinking_idle_images
+    }
+
+    pub fn blinking_speaking_images(&self) -> &Vec<ImageSource> {
+        &self.blinking_speaking_images
+    }
+
+    pub(crate) fn image_sources(&self) -> Vec<&ImageSource> {
+        self.idle_images
+            .iter()
+            .chain(&self.speaking_images)
+            .chain(&self.blinking_idle_images)
+            .chain(&self.blinking_speaking_images)
+            .collect()
+    }
+
+    pub(crate) fn image_sources_mut(&mut self) -> Vec<&mut ImageSource> {
+        self.idle_images
+            .iter_mut()
+            .chain(&mut self.speaking_images)
+            .chain(&mut self.blinking_idle_images)
+            .chain(&mut self.blinking_speaking_images)
+            .collect()
+    }
+}
@@ -0,0 +1,231 @@
+use std::str::FromStr;
+
+use better_default::Default;
+use serde::{Deserialize, Serialize};
+use serde_inline_default::serde_inline_default;
+
+use crate::{ArgsError, ValidArgs};
+
+/// How the avatar is scaled to the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitMode {
+    /// Shows the avatar at the size of its images.
+    #[default]
+    Native,
+    /// Scales the avatar to be as large as possible while still fitting into the window.
+    Contain,
+    /// Scales the avatar to fill the whole window, cutting off what doesn't fit.
+    Cover,
+    /// Scales the avatar by the largest whole number that still fits into the window, without
+    /// smoothing the pixels. This keeps pixel art crisp.
+    IntegerNearest,
+}
+
+/// Where in the window the avatar is placed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[serde_inline_default]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct WindowConfig {
+    /// The width and height the window opens with, in logical pixels. The frontend picks a size
+    /// when this is not set.
+    #[serde(default)]
+    size: Option<(u32, u32)>,
+    /// Scales the avatar on top of the fit mode.
+    #[default(1.0)]
+    #[serde_inline_default(1.0)]
+    scale: f32,
+    #[serde(default)]
+    fit: FitMode,
+    #[serde(default)]
+    anchor: Anchor,
+    /// Space kept free between the avatar and the edges of the window, in logical pixels.
+    #[serde(default)]
+    padding: f32,
+}
+
+impl WindowConfig {
+    pub fn size(&self) -> Option<(u32, u32)> {
+        self.size
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn fit(&self) -> FitMode {
+        self.fit
+    }
+
+    pub fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub fn padding(&self) -> f32 {
+        self.padding
+    }
+
+    /// Replaces any option that was also passed on the command line.
+    pub fn with_args(mut self, args: &ValidArgs) -> Self {
+        self.size = args.window_size().or(self.size);
+        self.scale = args.scale().unwrap_or(self.scale);
+        self.fit = args.fit().unwrap_or(self.fit);
+        self.anchor = args.anchor().unwrap_or(self.anchor);
+        self.padding = args.padding().unwrap_or(self.padding);
+        self
+    }
+
+    /// Works out how much an avatar of `size` is scaled by to be shown in an `area` of the window,
+    /// with both in logical pixels. The padding is taken out of the area first.
+    pub fn scale_for(
+        &self,
+        (width, height): (f32, f32),
+        (area_width, area_height): (f32, f32),
+    ) -> f32 {
+        if width <= 0.0 || height <= 0.0 {
+            return self.scale;
+        }
+        let horizontal = (area_width - self.padding * 2.0).max(0.0) / width;
+        let vertical = (area_height - self.padding * 2.0).max(0.0) / height;
+        match self.fit {
+            FitMode::Native => self.scale,
+            FitMode::Contain => horizontal.min(vertical) * self.scale,
+            FitMode::Cover => horizontal.max(vertical) * self.scale,
+            FitMode::IntegerNearest => (horizontal.min(vertical) * self.scale).floor().max(1.0),
+        }
+    }
+}
+
+impl Anchor {
+    /// Where the avatar is placed horizontally, from 0 for the left edge to 1 for the right edge.
+    pub fn horizontal(&self) -> f32 {
+        match self {
+            Anchor::Left | Anchor::TopLeft | Anchor::BottomLeft => 0.0,
+            Anchor::Center | Anchor::Top | Anchor::Bottom => 0.5,
+            Anchor::Right | Anchor::TopRight | Anchor::BottomRight => 1.0,
+        }
+    }
+
+    /// Where the avatar is placed vertically, from 0 for the top edge to 1 for the bottom edge.
+    pub fn vertical(&self) -> f32 {
+        match self {
+            Anchor::Top | Anchor::TopLeft | Anchor::TopRight => 0.0,
+            Anchor::Center | Anchor::Left | Anchor::Right => 0.5,
+            Anchor::Bottom | Anchor::BottomLeft | Anchor::BottomRight => 1.0,
+        }
+    }
+}
+
+impl FromStr for FitMode {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('_', "-").as_str() {
+            "native" => Ok(FitMode::Native),
+            "contain" => Ok(FitMode::Contain),
+            "cover" => Ok(FitMode::Cover),
+            "integer-nearest" => Ok(FitMode::IntegerNearest),
+            _ => Err(ArgsError::InvalidValue(String::from(s), "fit mode")),
+        }
+    }
+}
+
+impl FromStr for Anchor {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('_', "-").as_str() {
+            "center" => Ok(Anchor::Center),
+            "top" => Ok(Anchor::Top),
+            "bottom" => Ok(Anchor::Bottom),
+            "left" => Ok(Anchor::Left),
+            "right" => Ok(Anchor::Right),
+            "top-left" => Ok(Anchor::TopLeft),
+            "top-right" => Ok(Anchor::TopRight),
+            "bottom-left" => Ok(Anchor::BottomLeft),
+            "bottom-right" => Ok(Anchor::BottomRight),
+            _ => Err(ArgsError::InvalidValue(String::from(s), "anchor")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(fit: FitMode, scale: f32, padding: f32) -> WindowConfig {
+        WindowConfig {
+            fit,
+            scale,
+            padding,
+            ..WindowConfig::default()
+        }
+    }
+
+    #[test]
+    fn native_keeps_the_size_of_the_images() {
+        let window = window(FitMode::Native, 1.5, 10.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (1000.0, 20.0)), 1.5);
+    }
+
+    #[test]
+    fn contain_fits_the_avatar_into_the_area() {
+        let window = window(FitMode::Contain, 1.0, 0.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (400.0, 100.0)), 2.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (50.0, 100.0)), 0.5);
+    }
+
+    #[test]
+    fn cover_fills_the_area() {
+        let window = window(FitMode::Cover, 1.0, 0.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (400.0, 100.0)), 4.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (50.0, 100.0)), 2.0);
+    }
+
+    #[test]
+    fn integer_nearest_scales_by_whole_numbers() {
+        let window = window(FitMode::IntegerNearest, 1.0, 0.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (390.0, 500.0)), 3.0);
+        // The avatar is never scaled below its own size.
+        assert_eq!(window.scale_for((100.0, 50.0), (50.0, 500.0)), 1.0);
+    }
+
+    #[test]
+    fn the_scale_applies_on_top_of_the_fit_mode() {
+        assert_eq!(
+            window(FitMode::Contain, 0.5, 0.0).scale_for((100.0, 50.0), (400.0, 100.0)),
+            1.0
+        );
+        assert_eq!(
+            window(FitMode::IntegerNearest, 1.5, 0.0).scale_for((100.0, 50.0), (200.0, 100.0)),
+            3.0
+        );
+    }
+
+    #[test]
+    fn padding_is_taken_out_of_the_area() {
+        let window = window(FitMode::Contain, 1.0, 50.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (300.0, 300.0)), 2.0);
+        assert_eq!(window.scale_for((100.0, 50.0), (80.0, 80.0)), 0.0);
+    }
+
+    #[test]
+    fn empty_avatars_use_the_scale() {
+        let window = window(FitMode::Cover, 2.0, 0.0);
+        assert_eq!(window.scale_for((0.0, 50.0), (400.0, 100.0)), 2.0);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use crate::ArgsError;
+
+/// The file format a config is written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+    Ron,
+}
+
+impl ConfigFormat {
+    /// Works out the format from the extension of a config file. Returns `None` for unknown
+    /// extensions.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(ConfigFormat::Json),
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "ron" => Some(ConfigFormat::Ron),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            "ron" => Ok(ConfigFormat::Ron),
+            _ => Err(ArgsError::InvalidValue(String::from(s), "config format")),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigFormat::Json => "JSON",
+            ConfigFormat::Toml => "TOML",
+            ConfigFormat::Yaml => "YAML",
+            ConfigFormat::Ron => "RON",
+        })
+    }
+}
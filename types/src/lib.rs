@@ -1,7 +1,15 @@
 pub use cli::{Args, ArgsError, ValidArgs};
 pub use color::Color;
-pub use config::{AudioConfig, Config};
+pub use config::{
+    AfkConfig, Anchor, AudioConfig, BlinkConfig, ChromaKey, ClickReactionsConfig, Config, Easing,
+    EffectsConfig, ExpressionConfig, FitMode, FrameSequence, IdleAction, IdleActionsConfig,
+    ImageDirectory, ImageSource, LayerConfig, LoopMode, MotionConfig, MotionDriver, OutlineConfig,
+    PropConfig, ReactionConfig, SequenceFrame, ShadowConfig, SheetCell, SpringConfig, SpriteSheet,
+    TrackingConfig, TrackingMode, Transform, TransitionConfig, TransitionKind, TypingConfig,
+    WindowConfig,
+};
 pub use error::FrontendError;
+pub use format::ConfigFormat;
 pub use frontend::Frontend;
 pub use numbers::TwoInts;
 
@@ -11,5 +19,6 @@ mod cli;
 mod color;
 mod config;
 mod error;
+mod format;
 mod frontend;
 mod numbers;